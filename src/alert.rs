@@ -0,0 +1,311 @@
+//! Webhook 告警
+//!
+//! 守护进程主循环在进程意外退出、自动重启、崩溃循环、健康检查失败和调度任务失败时
+//! 调用 `Alerter::notify`。去重和限流在主循环中同步判断，HTTP 请求在独立任务中发送，
+//! 不阻塞主循环；发送失败只记录警告。
+
+use crate::config::{AlertConfig, AlertEvent, AlertKind};
+use crate::process_output::{parse_log_line, LogFilter, LogStream, LogViewer};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// 单次 webhook 请求超时
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 读取 stderr 之前等待日志写入任务落盘
+const LOG_FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+/// 通用 webhook 的消息体
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub event: AlertEvent,
+    pub process: String,
+    pub message: String,
+    pub host: String,
+    pub exit_code: Option<i32>,
+    /// 捕获日志中最后几行 stderr（仅退出类告警）
+    pub stderr: Vec<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn new(event: AlertEvent, process: &str, message: impl Into<String>) -> Self {
+        Self {
+            event,
+            process: process.to_string(),
+            message: message.into(),
+            host: sysinfo::System::host_name().unwrap_or_default(),
+            exit_code: None,
+            stderr: Vec::new(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    pub fn with_exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// 聊天机器人使用的纯文本内容
+    pub fn text(&self) -> String {
+        let mut text = format!("[mi7soft-daemon] {} {}: {}", self.host, self.process, self.message);
+        if !self.stderr.is_empty() {
+            text.push_str("\n\nstderr:\n");
+            text.push_str(&self.stderr.join("\n"));
+        }
+        text
+    }
+
+    /// 按 `kind` 要求的格式生成请求体
+    pub fn payload(&self, kind: AlertKind) -> serde_json::Value {
+        match kind {
+            AlertKind::Webhook => serde_json::to_value(self).unwrap_or_default(),
+            AlertKind::Dingtalk => json!({ "msgtype": "text", "text": { "content": self.text() } }),
+            AlertKind::Feishu => json!({ "msg_type": "text", "content": { "text": self.text() } }),
+            AlertKind::Slack => json!({ "text": self.text() }),
+        }
+    }
+}
+
+/// 单个告警目标及其去重、限流状态
+#[derive(Debug)]
+struct Target {
+    config: AlertConfig,
+    /// (进程, 事件) 最近一次发送时间
+    last_sent: HashMap<(String, AlertEvent), Instant>,
+    /// 最近一分钟内的发送时间
+    recent: VecDeque<Instant>,
+}
+
+impl Target {
+    /// 是否通过事件过滤、去重和限流
+    fn admit(&mut self, alert: &Alert, now: Instant) -> bool {
+        let config = &self.config;
+        if !config.events.is_empty() && !config.events.contains(&alert.event) {
+            return false;
+        }
+
+        let key = (alert.process.clone(), alert.event);
+        let dedup_window = Duration::from_secs(config.dedup_window);
+        if self.last_sent.get(&key).is_some_and(|last| now.duration_since(*last) < dedup_window) {
+            debug!(process = alert.process.as_str(), event = ?alert.event, "Duplicate alert suppressed");
+            return false;
+        }
+
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= config.rate_limit as usize {
+            warn!(process = alert.process.as_str(), event = ?alert.event, url = config.url.as_str(), "Alert rate limit reached, dropping alert");
+            return false;
+        }
+
+        self.last_sent.insert(key, now);
+        self.recent.push_back(now);
+        true
+    }
+}
+
+/// 按 `[[alerts]]` 配置发送告警
+pub struct Alerter {
+    targets: Vec<Target>,
+    client: reqwest::Client,
+}
+
+impl Alerter {
+    pub fn new(configs: &[AlertConfig]) -> Self {
+        let targets = configs
+            .iter()
+            .map(|config| Target { config: config.clone(), last_sent: HashMap::new(), recent: VecDeque::new() })
+            .collect();
+        let client = reqwest::Client::builder()
+            .timeout(SEND_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { targets, client }
+    }
+
+    /// 向所有接受该告警的目标发送
+    ///
+    /// `log_file` 为进程的输出捕获日志，指定时在发送前附加最后几行 stderr
+    pub fn notify(&mut self, alert: Alert, log_file: Option<String>) {
+        let now = Instant::now();
+        let targets: Vec<AlertConfig> = self.targets
+            .iter_mut()
+            .filter_map(|t| t.admit(&alert, now).then(|| t.config.clone()))
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let mut alert = alert;
+            let stderr_lines = targets.iter().map(|t| t.stderr_lines).max().unwrap_or(0);
+            if let (Some(log_file), true) = (log_file, stderr_lines > 0) {
+                tokio::time::sleep(LOG_FLUSH_DELAY).await;
+                alert.stderr = stderr_tail(PathBuf::from(log_file), stderr_lines).await;
+            }
+
+            for target in targets {
+                let mut alert = alert.clone();
+                let skip = alert.stderr.len().saturating_sub(target.stderr_lines);
+                alert.stderr.drain(..skip);
+                send(&client, &target, &alert).await;
+            }
+        });
+    }
+}
+
+async fn stderr_tail(log_file: PathBuf, lines: usize) -> Vec<String> {
+    let filter = LogFilter { stream: Some(LogStream::Stderr), ..Default::default() };
+    match LogViewer::new(log_file).filter(&filter, Some(lines)).await {
+        Ok(tail) => tail
+            .into_iter()
+            .map(|line| parse_log_line(&line).map(|l| l.content).unwrap_or(line))
+            .collect(),
+        Err(e) => {
+            debug!(error = %e, "Failed to read stderr for alert");
+            Vec::new()
+        }
+    }
+}
+
+async fn send(client: &reqwest::Client, target: &AlertConfig, alert: &Alert) {
+    let result = client
+        .post(&target.url)
+        .json(&alert.payload(target.kind))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match result {
+        Ok(_) => debug!(process = alert.process.as_str(), event = ?alert.event, url = target.url.as_str(), "Alert sent"),
+        Err(e) => warn!(process = alert.process.as_str(), event = ?alert.event, error = %e, "Failed to send alert"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+    use tokio::sync::mpsc;
+
+    fn config(kind: AlertKind, url: &str) -> AlertConfig {
+        AlertConfig {
+            kind,
+            url: url.to_string(),
+            events: Vec::new(),
+            dedup_window: 300,
+            rate_limit: 10,
+            stderr_lines: 2,
+        }
+    }
+
+    /// 本地 webhook 接收端，收到的请求体转发到通道
+    async fn receiver() -> (String, mpsc::UnboundedReceiver<(String, serde_json::Value)>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = Router::new()
+            .route("/{kind}", post(
+                |State(tx): State<mpsc::UnboundedSender<(String, serde_json::Value)>>,
+                 axum::extract::Path(kind): axum::extract::Path<String>,
+                 Json(body): Json<serde_json::Value>| async move {
+                    let _ = tx.send((kind, body));
+                },
+            ))
+            .with_state(tx);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, rx)
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<(String, serde_json::Value)>) -> (String, serde_json::Value) {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_admit_filters_dedups_and_rate_limits() {
+        let mut target = Target {
+            config: AlertConfig {
+                events: vec![AlertEvent::Exit, AlertEvent::Restart],
+                rate_limit: 2,
+                ..config(AlertKind::Webhook, "http://127.0.0.1")
+            },
+            last_sent: HashMap::new(),
+            recent: VecDeque::new(),
+        };
+        let now = Instant::now();
+        let alert = |event, process| Alert::new(event, process, "test");
+
+        assert!(!target.admit(&alert(AlertEvent::HealthCheck, "web"), now));
+        assert!(target.admit(&alert(AlertEvent::Exit, "web"), now));
+        // 同一进程的同类告警被去重
+        assert!(!target.admit(&alert(AlertEvent::Exit, "web"), now + Duration::from_secs(10)));
+        assert!(target.admit(&alert(AlertEvent::Restart, "web"), now));
+        // 一分钟内超过 rate_limit
+        assert!(!target.admit(&alert(AlertEvent::Exit, "worker"), now + Duration::from_secs(30)));
+        assert!(target.admit(&alert(AlertEvent::Exit, "worker"), now + Duration::from_secs(60)));
+        // 去重窗口过后再次发送
+        assert!(target.admit(&alert(AlertEvent::Exit, "web"), now + Duration::from_secs(300)));
+    }
+
+    #[tokio::test]
+    async fn test_send_with_stderr_tail() {
+        let (url, mut rx) = receiver().await;
+        let mut log = NamedTempFile::new().unwrap();
+        writeln!(
+            log,
+            "[2026-03-01 10:00:00] [ERR] first\n[2026-03-01 10:00:01] [OUT] ignored\n\
+             [2026-03-01 10:00:02] [ERR] second\n[2026-03-01 10:00:03] [ERR] Fatal error: boom"
+        )
+        .unwrap();
+
+        let mut alerter = Alerter::new(&[
+            config(AlertKind::Webhook, &format!("{}/webhook", url)),
+            config(AlertKind::Slack, &format!("{}/slack", url)),
+        ]);
+        let alert = Alert::new(AlertEvent::Exit, "web", "exited unexpectedly: exit code 255").with_exit_code(Some(255));
+        alerter.notify(alert.clone(), Some(log.path().to_string_lossy().to_string()));
+
+        let mut received: HashMap<String, serde_json::Value> = HashMap::new();
+        for _ in 0..2 {
+            let (kind, body) = next(&mut rx).await;
+            received.insert(kind, body);
+        }
+        let webhook = &received["webhook"];
+        assert_eq!(webhook["event"], "exit");
+        assert_eq!(webhook["process"], "web");
+        assert_eq!(webhook["exit_code"], 255);
+        assert_eq!(webhook["stderr"], json!(["second", "Fatal error: boom"]));
+        let text = received["slack"]["text"].as_str().unwrap();
+        assert!(text.contains("web: exited unexpectedly") && text.ends_with("Fatal error: boom"), "{}", text);
+
+        // 去重：相同告警不再发送，其他事件照常发送
+        alerter.notify(alert, None);
+        alerter.notify(Alert::new(AlertEvent::CrashLoop, "web", "crash loop"), None);
+        for _ in 0..2 {
+            let (_, body) = next(&mut rx).await;
+            assert!(body.to_string().contains("crash loop"), "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_chat_payloads() {
+        let alert = Alert::new(AlertEvent::JobFailed, "backup", "job failed");
+        let dingtalk = alert.payload(AlertKind::Dingtalk);
+        assert_eq!(dingtalk["msgtype"], "text");
+        assert!(dingtalk["text"]["content"].as_str().unwrap().ends_with("backup: job failed"));
+        let feishu = alert.payload(AlertKind::Feishu);
+        assert_eq!(feishu["msg_type"], "text");
+        assert_eq!(feishu["content"]["text"], dingtalk["text"]["content"]);
+    }
+}
@@ -0,0 +1,392 @@
+//! HTTP 管理 API
+//!
+//! 为 `web/` 前端提供 REST/JSON 接口，监听地址和访问令牌在 `[daemon]` 中配置。
+//! 与控制套接字共用请求通道：每个请求转换为 `ControlCommand` 交给 `Daemon::run`
+//! 主循环处理，成功时返回与控制协议相同的 JSON 响应；日志跟踪以 Server-Sent Events 推送。
+
+use crate::control::{self, ControlCommand, ControlResponse, PendingRequest};
+use crate::error::DaemonError;
+use crate::process_output::LogViewer;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+#[derive(Clone)]
+struct ApiState {
+    requests: mpsc::UnboundedSender<PendingRequest>,
+    token: Arc<str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    #[serde(default = "default_history_limit")]
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogQuery {
+    #[serde(default = "default_log_lines")]
+    lines: usize,
+}
+
+fn default_history_limit() -> usize {
+    20
+}
+
+fn default_log_lines() -> usize {
+    100
+}
+
+/// Bind the API listener and serve it in the background
+pub async fn serve(
+    addr: &str,
+    token: String,
+    requests: mpsc::UnboundedSender<PendingRequest>,
+) -> Result<SocketAddr, DaemonError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| DaemonError::Control(format!("Failed to bind HTTP API on {}: {}", addr, e)))?;
+    let local_addr = listener.local_addr()?;
+
+    let app = router(ApiState { requests, token: token.into() });
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!(error = %e, "HTTP API stopped");
+        }
+    });
+
+    info!(addr = %local_addr, "HTTP API listening");
+    Ok(local_addr)
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/processes", get(list))
+        .route("/api/processes/{name}", get(status))
+        .route("/api/processes/{name}/start", post(start))
+        .route("/api/processes/{name}/stop", post(stop))
+        .route("/api/processes/{name}/restart", post(restart))
+        .route("/api/processes/{name}/reload", post(reload_process))
+        .route("/api/processes/{name}/history", get(history))
+        .route("/api/processes/{name}/logs", get(logs))
+        .route("/api/processes/{name}/logs/follow", get(follow_logs))
+        .route("/api/reload", post(reload))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Require `Authorization: Bearer <token>`, or `?token=` on the log follow stream
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    // 浏览器的 EventSource 无法设置请求头，只有 SSE 接口接受查询参数，避免令牌出现在其他请求的访问日志中
+    let query = if is_follow_path(request.uri().path()) {
+        Query::<TokenQuery>::try_from_uri(request.uri()).ok().and_then(|q| q.0.token)
+    } else {
+        None
+    };
+
+    match bearer.or(query) {
+        Some(token) if token_matches(&token, &state.token) => next.run(request).await,
+        _ => error_response(StatusCode::UNAUTHORIZED, "missing or invalid API token"),
+    }
+}
+
+/// Whether the path is `/api/processes/{name}/logs/follow`
+fn is_follow_path(path: &str) -> bool {
+    path.strip_prefix("/api/processes/")
+        .and_then(|rest| rest.strip_suffix("/logs/follow"))
+        .is_some_and(|name| !name.is_empty() && !name.contains('/'))
+}
+
+/// Compare without returning early on the first mismatching byte
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn list(State(state): State<ApiState>) -> Response {
+    call(&state, ControlCommand::Status { name: None }).await
+}
+
+async fn status(State(state): State<ApiState>, Path(name): Path<String>) -> Response {
+    call(&state, ControlCommand::Status { name: Some(name) }).await
+}
+
+async fn start(State(state): State<ApiState>, Path(name): Path<String>) -> Response {
+    call(&state, ControlCommand::Start { name }).await
+}
+
+async fn stop(State(state): State<ApiState>, Path(name): Path<String>) -> Response {
+    call(&state, ControlCommand::Stop { name }).await
+}
+
+async fn restart(State(state): State<ApiState>, Path(name): Path<String>) -> Response {
+    call(&state, ControlCommand::Restart { name }).await
+}
+
+async fn reload_process(State(state): State<ApiState>, Path(name): Path<String>) -> Response {
+    call(&state, ControlCommand::ReloadProcess { name }).await
+}
+
+async fn reload(State(state): State<ApiState>) -> Response {
+    call(&state, ControlCommand::Reload).await
+}
+
+async fn history(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    call(&state, ControlCommand::History { name, limit: query.limit }).await
+}
+
+async fn logs(State(state): State<ApiState>, Path(name): Path<String>, Query(query): Query<LogQuery>) -> Response {
+    let path = match log_file(&state, name).await {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    match LogViewer::new(path).tail(query.lines).await {
+        Ok(lines) => Json(serde_json::json!({ "lines": lines })).into_response(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            error_response(StatusCode::NOT_FOUND, "log file does not exist yet")
+        }
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Stream the last `lines` lines and then every appended line as SSE `data` events
+async fn follow_logs(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    Query(query): Query<LogQuery>,
+) -> Response {
+    let path = match log_file(&state, name).await {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    let receiver = match LogViewer::new(path).follow(query.lines).await {
+        Ok(receiver) => receiver,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+    // 客户端断开时 stream 被 drop，跟踪任务随接收端关闭而退出
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let line = receiver.recv().await?;
+        Some((Ok::<_, Infallible>(Event::default().data(line)), receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn log_file(state: &ApiState, name: String) -> Result<PathBuf, Response> {
+    match control::dispatch(&state.requests, ControlCommand::LogFile { name }).await {
+        Some(ControlResponse::LogFile { path }) => Ok(PathBuf::from(path)),
+        other => Err(into_response(other)),
+    }
+}
+
+async fn call(state: &ApiState, command: ControlCommand) -> Response {
+    into_response(control::dispatch(&state.requests, command).await)
+}
+
+fn into_response(response: Option<ControlResponse>) -> Response {
+    match response {
+        Some(ControlResponse::Error { message, exit_code }) => error_response(status_for(exit_code), &message),
+        Some(response) => Json(response).into_response(),
+        None => error_response(StatusCode::SERVICE_UNAVAILABLE, "daemon is not responding"),
+    }
+}
+
+/// Map the sysexits code of a daemon error to an HTTP status
+fn status_for(exit_code: i32) -> StatusCode {
+    match exit_code {
+        1 => StatusCode::CONFLICT,          // 已在运行 / 未运行
+        78 => StatusCode::BAD_REQUEST,      // 配置错误、进程不存在
+        69 | 75 => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{ProcessState, ProcessStatus};
+    use std::io::Write;
+    use std::time::Duration;
+    use tempfile::NamedTempFile;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+    use tokio::net::TcpStream;
+
+    const TOKEN: &str = "secret";
+
+    async fn start_api(log_file: String) -> SocketAddr {
+        start_api_with_token(log_file, TOKEN).await
+    }
+
+    /// 模拟守护进程主循环，只认识名为 "web" 的进程
+    async fn start_api_with_token(log_file: String, token: &str) -> SocketAddr {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<PendingRequest>();
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let response = match request.command.clone() {
+                    ControlCommand::Status { name: None } => ControlResponse::Status {
+                        processes: vec![ProcessStatus {
+                            name: "web".to_string(),
+                            pid: 42,
+                            state: ProcessState::Running,
+                            uptime: 5,
+                            memory: None,
+                            group_memory: None,
+                            cpu: None,
+                            threads: None,
+                            fds: None,
+                            restarts: 0,
+                            next_run: None,
+                            cgroup: None,
+                            instance: None,
+                        }],
+                    },
+                    ControlCommand::Start { name } if name == "web" => ControlResponse::from_error(
+                        &DaemonError::AlreadyRunning { name, pid: 42 },
+                    ),
+                    ControlCommand::Stop { name } if name == "web" => ControlResponse::Stopped { pids: vec![42] },
+                    ControlCommand::History { limit, .. } => {
+                        assert_eq!(limit, 5);
+                        ControlResponse::History { records: Vec::new() }
+                    }
+                    ControlCommand::LogFile { name } if name == "web" => {
+                        ControlResponse::LogFile { path: log_file.clone() }
+                    }
+                    _ => ControlResponse::from_error(&DaemonError::Config("Process not found in config".to_string())),
+                };
+                request.respond(response);
+            }
+        });
+        serve("127.0.0.1:0", token.to_string(), sender).await.unwrap()
+    }
+
+    async fn send(addr: SocketAddr, method: &str, path: &str, token: Option<&str>) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let auth = token.map(|t| format!("Authorization: Bearer {}\r\n", t)).unwrap_or_default();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+            method, path, auth
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        stream
+    }
+
+    async fn request(addr: SocketAddr, method: &str, path: &str, token: Option<&str>) -> (u16, String) {
+        let mut stream = send(addr, method, path, token).await;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let body = response.split_once("\r\n\r\n").map(|(_, b)| b.to_string()).unwrap_or_default();
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_auth_and_operations() {
+        let addr = start_api("/nonexistent/web.log".to_string()).await;
+
+        assert_eq!(request(addr, "GET", "/api/processes", None).await.0, 401);
+        assert_eq!(request(addr, "GET", "/api/processes", Some("wrong")).await.0, 401);
+
+        let (status, body) = request(addr, "GET", "/api/processes", Some(TOKEN)).await;
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["result"], "status");
+        assert_eq!(json["processes"][0]["pid"], 42);
+
+        let (status, body) = request(addr, "POST", "/api/processes/web/stop", Some(TOKEN)).await;
+        assert_eq!((status, body.as_str()), (200, r#"{"result":"stopped","pids":[42]}"#));
+
+        let (status, body) = request(addr, "POST", "/api/processes/web/start", Some(TOKEN)).await;
+        assert_eq!(status, 409);
+        assert!(body.contains("already running"), "{}", body);
+
+        assert_eq!(request(addr, "POST", "/api/processes/nope/restart", Some(TOKEN)).await.0, 400);
+        assert_eq!(request(addr, "GET", "/api/processes/web/history?limit=5", Some(TOKEN)).await.0, 200);
+        assert_eq!(request(addr, "GET", "/api/processes/web/logs", Some(TOKEN)).await.0, 404);
+        assert_eq!(request(addr, "GET", "/api/processes/web/stop", Some(TOKEN)).await.0, 405);
+
+        // 查询参数中的令牌只用于 SSE 接口
+        assert_eq!(request(addr, "GET", &format!("/api/processes?token={}", TOKEN), None).await.0, 401);
+        assert_eq!(request(addr, "POST", &format!("/api/processes/web/stop?token={}", TOKEN), None).await.0, 401);
+    }
+
+    #[test]
+    fn test_follow_path() {
+        assert!(is_follow_path("/api/processes/web/logs/follow"));
+        assert!(is_follow_path("/api/processes/worker:1/logs/follow"));
+        assert!(!is_follow_path("/api/processes/web/logs"));
+        assert!(!is_follow_path("/api/processes//logs/follow"));
+        assert!(!is_follow_path("/api/processes/a/b/logs/follow"));
+    }
+
+    #[tokio::test]
+    async fn test_logs_tail_and_follow() {
+        let mut log = NamedTempFile::new().unwrap();
+        writeln!(log, "one\ntwo\nthree").unwrap();
+        let addr = start_api(log.path().to_string_lossy().to_string()).await;
+
+        let (status, body) = request(addr, "GET", "/api/processes/web/logs?lines=2", Some(TOKEN)).await;
+        assert_eq!((status, body.as_str()), (200, r#"{"lines":["two","three"]}"#));
+
+        let stream = send(addr, "GET", &format!("/api/processes/web/logs/follow?lines=1&token={}", TOKEN), None).await;
+        let mut lines = BufReader::new(stream).lines();
+        async fn next_data(lines: &mut Lines<BufReader<TcpStream>>) -> String {
+            loop {
+                let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .unwrap();
+                if let Some(data) = line.strip_prefix("data: ") {
+                    return data.to_string();
+                }
+            }
+        }
+        assert_eq!(next_data(&mut lines).await, "three");
+        writeln!(log, "four").unwrap();
+        assert_eq!(next_data(&mut lines).await, "four");
+    }
+
+    #[tokio::test]
+    async fn test_follow_token_percent_decoded() {
+        let log = NamedTempFile::new().unwrap();
+        let addr = start_api_with_token(log.path().to_string_lossy().to_string(), "a&b=c+d%").await;
+
+        let status = |encoded: &'static str| async move {
+            let stream = send(addr, "GET", &format!("/api/processes/web/logs/follow?token={}", encoded), None).await;
+            let line = BufReader::new(stream).lines().next_line().await.unwrap().unwrap();
+            line.split_whitespace().nth(1).unwrap().to_string()
+        };
+        assert_eq!(status("a%26b%3Dc%2Bd%25").await, "200");
+        assert_eq!(status("a&b=c+d%").await, "401");
+    }
+}
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use crate::process_output::{parse_time_spec, LogStream};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -49,27 +51,52 @@ pub enum Commands {
     Status {
         /// Process name (optional, shows all if not specified)
         name: Option<String>,
+        
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
     },
     
     /// Shutdown the daemon
     Shutdown,
     
+    /// Reload the daemon configuration
+    ReloadConfig,
+    
+    /// Send the reload signal to a process without restarting it
+    Reload {
+        /// Process name (or name:instance)
+        name: String,
+    },
+    
     /// View process logs
     Logs {
         /// Process name
         name: String,
         
-        /// Number of lines to show
-        #[arg(short = 'n', long, default_value = "100")]
-        lines: usize,
+        /// Number of lines to show (default 100, unlimited when filtering by time)
+        #[arg(short = 'n', long)]
+        lines: Option<usize>,
         
         /// Follow log output (like tail -f)
         #[arg(short, long)]
         follow: bool,
         
-        /// Show logs since N seconds ago
-        #[arg(long)]
+        /// Show logs since a duration ago (30s, 10m, 2h, 1d) or a UTC timestamp
+        #[arg(long, value_parser = parse_time_arg)]
         since: Option<i64>,
+        
+        /// Show logs until a duration ago (30s, 10m, 2h, 1d) or a UTC timestamp
+        #[arg(long, value_parser = parse_time_arg)]
+        until: Option<i64>,
+        
+        /// Only show one output stream
+        #[arg(long, value_enum)]
+        stream: Option<StreamArg>,
+        
+        /// Only show lines matching a regular expression
+        #[arg(long)]
+        grep: Option<regex::Regex>,
     },
     
     /// View process history
@@ -80,7 +107,106 @@ pub enum Commands {
         /// Number of records to show
         #[arg(short = 'n', long, default_value = "10")]
         number: usize,
+        
+        /// Output format
+        #[arg(short, long, value_parser = output_without_wide(), default_value = "table")]
+        output: OutputFormat,
+    },
+    
+    /// Show recent runs of a scheduled process with their results
+    Jobs {
+        /// Process name
+        name: String,
+        
+        /// Number of runs to show
+        #[arg(short = 'n', long, default_value = "10")]
+        number: usize,
+        
+        /// Output format
+        #[arg(short, long, value_parser = output_without_wide(), default_value = "table")]
+        output: OutputFormat,
+    },
+    
+    /// Show process statistics (starts, restarts, failures, average uptime)
+    Stats {
+        /// Process name (optional, shows all if not specified)
+        name: Option<String>,
+    },
+    
+    /// Preview and trigger scheduled processes
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
     },
+    
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Validate the config, including commands, working directories and log paths
+    Check,
+}
+
+#[derive(Subcommand)]
+pub enum ScheduleAction {
+    /// List scheduled processes with their next fire times and last result
+    List {
+        /// Number of upcoming runs to show per process
+        #[arg(short = 'n', long, default_value = "3")]
+        count: usize,
+        
+        /// Output format
+        #[arg(short, long, value_parser = output_without_wide(), default_value = "table")]
+        output: OutputFormat,
+    },
+    
+    /// Run a scheduled process now, respecting its concurrency policy
+    Run {
+        /// Process name
+        name: String,
+    },
+}
+
+/// Output format for `status` and the other listing commands
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable table
+    Table,
+    /// Table with command line, log file and next run
+    Wide,
+    Json,
+    Yaml,
+}
+
+/// Output stream selector for `logs --stream`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StreamArg {
+    Stdout,
+    Stderr,
+}
+
+impl From<StreamArg> for LogStream {
+    fn from(arg: StreamArg) -> Self {
+        match arg {
+            StreamArg::Stdout => LogStream::Stdout,
+            StreamArg::Stderr => LogStream::Stderr,
+        }
+    }
+}
+
+/// `--output` for commands that have no wide table: table, json or yaml
+fn output_without_wide() -> impl TypedValueParser<Value = OutputFormat> {
+    PossibleValuesParser::new(["table", "json", "yaml"])
+        .map(|value| OutputFormat::from_str(&value, true).expect("listed in possible values"))
+}
+
+fn parse_time_arg(value: &str) -> Result<i64, String> {
+    parse_time_spec(value, chrono::Utc::now())
 }
 
 impl Cli {
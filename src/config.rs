@@ -1,15 +1,102 @@
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use crate::error::DaemonError;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Schedule {
     #[serde(rename = "type")]
     pub schedule_type: ScheduleType,
     pub interval: Option<u64>,
     pub expression: Option<String>,
+    /// cron 表达式使用的时区（IANA 名称，如 "Asia/Shanghai"），默认 UTC
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// 上一次执行尚未结束时如何处理新的执行
+    #[serde(default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+    /// 守护进程重启后补跑错过的执行：错过的时间点距今不超过该值（秒）时立即执行一次
+    #[serde(default)]
+    pub starting_deadline: Option<u64>,
+    /// 单次执行的最长运行时间（秒），超时后按 stop_signal/stop_timeout 终止
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// 每次执行前的最大随机延迟（秒），避免多台主机同时执行
+    #[serde(default)]
+    pub jitter: Option<u64>,
+}
+
+impl Schedule {
+    /// Parsed `timezone`, UTC when not set
+    pub fn tz(&self) -> Result<chrono_tz::Tz, DaemonError> {
+        match self.timezone {
+            Some(ref name) => name.parse().map_err(|_| DaemonError::Config(format!("Unknown timezone '{}'", name))),
+            None => Ok(chrono_tz::UTC),
+        }
+    }
+    
+    /// Check that the fields match the schedule type, returning a message without the process name
+    fn validate(&self) -> Result<(), String> {
+        match self.schedule_type {
+            ScheduleType::Cron => {
+                let expr = self.expression.as_deref().ok_or("cron schedule requires 'expression'")?;
+                let fields = expr.split_whitespace().count();
+                if !(6..=7).contains(&fields) {
+                    return Err(format!(
+                        "invalid cron expression '{}': expected 6 or 7 fields (sec min hour day month weekday [year]), got {}",
+                        expr, fields
+                    ));
+                }
+                // H 只影响取值，校验时使用任意种子即可
+                let resolved = crate::process::Scheduler::resolve_expression(expr, "")
+                    .map_err(|e| format!("invalid cron expression '{}': {}", expr, e))?;
+                // cron 的错误信息前两行是表达式和指向出错位置的 ^
+                cron::Schedule::from_str(&resolved).map_err(|e| format!(
+                    "invalid cron expression '{}': {}", expr, e.to_string().lines().nth(2).unwrap_or_default()
+                ))?;
+                if self.interval.is_some() {
+                    return Err("'interval' is not used by cron schedules".to_string());
+                }
+            }
+            ScheduleType::Interval => {
+                match self.interval {
+                    None => return Err("interval schedule requires 'interval'".to_string()),
+                    Some(0) => return Err("'interval' must be greater than 0".to_string()),
+                    Some(_) => {}
+                }
+                if self.expression.is_some() {
+                    return Err("'expression' is not used by interval schedules".to_string());
+                }
+                if self.timezone.is_some() {
+                    return Err("'timezone' only applies to cron schedules".to_string());
+                }
+            }
+        }
+        if let Some(ref name) = self.timezone {
+            self.tz().map_err(|_| format!("unknown timezone '{}'", name))?;
+        }
+        for (key, value) in [("timeout", self.timeout), ("starting_deadline", self.starting_deadline), ("jitter", self.jitter)] {
+            if value == Some(0) {
+                return Err(format!("'{}' must be greater than 0", key));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 调度任务的并发策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConcurrencyPolicy {
+    /// 允许多次执行同时运行（受 max_instances 限制）
+    #[default]
+    Allow,
+    /// 上一次执行仍在运行时跳过本次
+    Forbid,
+    /// 停止上一次执行后再开始本次
+    Replace,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -20,11 +107,198 @@ pub enum ScheduleType {
     Cron,
 }
 
+/// 健康检查配置（`[processes.health_check]`）
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HealthCheck {
+    #[serde(flatten)]
+    pub probe: HealthProbe,
+    
+    /// 两次检查之间的间隔（秒）
+    #[serde(default = "default_health_interval")]
+    pub interval: u64,
+    
+    /// 单次检查超时（秒）
+    #[serde(default = "default_health_timeout")]
+    pub timeout: u64,
+    
+    /// 连续失败多少次后重启进程
+    #[serde(default = "default_health_retries")]
+    pub retries: u32,
+    
+    /// 进程启动后多少秒内不做检查
+    #[serde(default)]
+    pub start_period: u64,
+}
+
+/// 健康检查探针类型
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HealthProbe {
+    /// HTTP GET 本地端口，检查响应状态码
+    Http {
+        #[serde(default = "default_health_host")]
+        host: String,
+        port: u16,
+        #[serde(default = "default_health_path")]
+        path: String,
+        #[serde(default = "default_expected_status")]
+        expected_status: u16,
+    },
+    /// TCP 连接
+    Tcp {
+        #[serde(default = "default_health_host")]
+        host: String,
+        port: u16,
+    },
+    /// 执行命令，退出码为 0 视为健康
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// 心跳文件的修改时间不超过 max_age 秒
+    File {
+        path: String,
+        max_age: u64,
+    },
+}
+
+fn default_health_interval() -> u64 {
+    10
+}
+
+fn default_health_timeout() -> u64 {
+    5
+}
+
+fn default_health_retries() -> u32 {
+    3
+}
+
+fn default_health_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_health_path() -> String {
+    "/".to_string()
+}
+
+fn default_expected_status() -> u16 {
+    200
+}
+
+/// 资源限制（`[processes.limits]`）
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ResourceLimits {
+    /// RLIMIT_NOFILE：最大打开文件数
+    #[serde(default)]
+    pub nofile: Option<u64>,
+    
+    /// RLIMIT_NPROC：用户最大进程数
+    #[serde(default)]
+    pub nproc: Option<u64>,
+    
+    /// RLIMIT_CORE：core 文件大小上限（字节）
+    #[serde(default)]
+    pub core: Option<u64>,
+    
+    /// RLIMIT_AS：虚拟地址空间上限（字节）
+    #[serde(default, rename = "as")]
+    pub address_space: Option<u64>,
+    
+    /// cgroup memory.max（字节）
+    #[serde(default)]
+    pub memory_max: Option<u64>,
+    
+    /// cgroup cpu.max，以 CPU 核数表示，例如 1.5
+    #[serde(default)]
+    pub cpu_max: Option<f64>,
+    
+    /// cgroup pids.max
+    #[serde(default)]
+    pub pids_max: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Whether any limit needs a cgroup
+    pub fn has_cgroup_limits(&self) -> bool {
+        self.memory_max.is_some() || self.cpu_max.is_some() || self.pids_max.is_some()
+    }
+}
+
+/// 告警目标（`[[alerts]]`）
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AlertConfig {
+    /// 消息格式，默认通用 JSON webhook
+    #[serde(rename = "type", default)]
+    pub kind: AlertKind,
+    
+    pub url: String,
+    
+    /// 需要告警的事件，为空表示全部
+    #[serde(default)]
+    pub events: Vec<AlertEvent>,
+    
+    /// 同一进程的同类告警在该时间（秒）内只发送一次
+    #[serde(default = "default_alert_dedup_window")]
+    pub dedup_window: u64,
+    
+    /// 每分钟最多发送的告警数
+    #[serde(default = "default_alert_rate_limit")]
+    pub rate_limit: u32,
+    
+    /// 退出告警附带的 stderr 行数
+    #[serde(default = "default_alert_stderr_lines")]
+    pub stderr_lines: usize,
+}
+
+/// 告警消息格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertKind {
+    /// 通用 JSON，字段见 `alert::Alert`
+    #[default]
+    Webhook,
+    Dingtalk,
+    Feishu,
+    Slack,
+}
+
+/// 触发告警的生命周期事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEvent {
+    /// 进程意外退出
+    Exit,
+    /// 自动重启（退避重启、看门狗重启）
+    Restart,
+    /// 崩溃循环，不再自动重启
+    CrashLoop,
+    /// 健康检查连续失败
+    HealthCheck,
+    /// 调度任务以非 0 状态结束
+    JobFailed,
+}
+
+fn default_alert_dedup_window() -> u64 {
+    300
+}
+
+fn default_alert_rate_limit() -> u32 {
+    10
+}
+
+fn default_alert_stderr_lines() -> usize {
+    20
+}
+
 /// Daemon configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DaemonConfig {
     pub daemon: Option<DaemonSettings>,
     pub processes: Vec<ProcessConfig>,
+    #[serde(default)]
+    pub alerts: Vec<AlertConfig>,
 }
 
 /// Daemon-specific settings
@@ -38,6 +312,26 @@ pub struct DaemonSettings {
     
     #[serde(default = "default_check_interval")]
     pub check_interval: u64,
+    
+    /// 历史记录保留天数
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u32,
+    
+    /// 为每个进程创建 cgroup 的父目录（cgroup v2）
+    #[serde(default = "default_cgroup_parent")]
+    pub cgroup_parent: String,
+    
+    /// Prometheus 指标监听地址，如 "127.0.0.1:9101"；未配置时不监听
+    #[serde(default)]
+    pub metrics_addr: Option<String>,
+    
+    /// HTTP 管理 API 监听地址；未配置时不监听
+    #[serde(default)]
+    pub api_addr: Option<String>,
+    
+    /// HTTP 管理 API 的访问令牌，配置了 api_addr 时必填
+    #[serde(default)]
+    pub api_token: Option<String>,
 }
 
 fn default_pid_file() -> String {
@@ -52,6 +346,14 @@ fn default_check_interval() -> u64 {
     5
 }
 
+fn default_history_retention_days() -> u32 {
+    30
+}
+
+fn default_cgroup_parent() -> String {
+    crate::limits::DEFAULT_CGROUP_PARENT.to_string()
+}
+
 /// Process configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProcessConfig {
@@ -77,14 +379,30 @@ pub struct ProcessConfig {
     #[serde(default)]
     pub max_instances: Option<usize>,
     
+    /// 保持运行的实例数，每个实例通过 M7D_INSTANCE 和 `{instance}` 模板区分
+    #[serde(default = "default_instances")]
+    pub instances: usize,
+    
     /// 是否捕获进程输出（新增）
     #[serde(default = "default_capture_output")]
     pub capture_output: bool,
     
-    /// 最大日志文件大小（字节）（新增）
+    /// 最大日志文件大小（字节），超过后轮转
     #[serde(default)]
     pub max_log_size: Option<u64>,
     
+    /// 按时间轮转日志的间隔（秒），可选
+    #[serde(default)]
+    pub log_rotate_interval: Option<u64>,
+    
+    /// 轮转后保留的历史日志文件数量
+    #[serde(default = "default_max_log_files")]
+    pub max_log_files: usize,
+    
+    /// 是否 gzip 压缩轮转后的日志文件
+    #[serde(default)]
+    pub compress_logs: bool,
+    
     /// 进程级别的检查间隔（秒），可选，若不配置则使用全局 check_interval
     #[serde(default)]
     pub check_interval: Option<u64>,
@@ -92,12 +410,179 @@ pub struct ProcessConfig {
     /// 调度模式：interval 或 cron
     #[serde(default)]
     pub schedule: Option<Schedule>,
+    
+    /// 首次自动重启前的等待时间（秒）
+    #[serde(default = "default_restart_delay")]
+    pub restart_delay: u64,
+    
+    /// 连续启动失败时等待时间的增长倍数
+    #[serde(default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    
+    /// 重启等待时间上限（秒）
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: u64,
+    
+    /// restart_window 内允许的最大重启次数，超过后进入 fatal 状态
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    
+    /// 统计重启次数的时间窗口（秒）
+    #[serde(default = "default_restart_window")]
+    pub restart_window: u64,
+    
+    /// 运行时间低于该值（秒）的退出视为启动失败
+    #[serde(default = "default_min_uptime")]
+    pub min_uptime: u64,
+    
+    /// 健康检查，用于发现进程存活但已挂起的情况
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+    
+    /// 以指定用户运行（用户名或 UID）
+    #[serde(default)]
+    pub user: Option<String>,
+    
+    /// 以指定用户组运行（组名或 GID），默认使用 user 的主组
+    #[serde(default)]
+    pub group: Option<String>,
+    
+    /// 附加用户组，默认使用 user 所属的全部组
+    #[serde(default)]
+    pub supplementary_groups: Vec<String>,
+    
+    /// 子进程的 umask，八进制字符串，例如 "022"
+    #[serde(default)]
+    pub umask: Option<String>,
+    
+    /// rlimit 与 cgroup 资源限制
+    #[serde(default)]
+    pub limits: Option<ResourceLimits>,
+    
+    /// 看门狗：RSS 超过该值（字节）时重启
+    #[serde(default)]
+    pub max_memory: Option<u64>,
+    
+    /// 看门狗：cpu_window 内平均 CPU 占用超过该百分比时重启（100 表示一个核）
+    #[serde(default)]
+    pub max_cpu_percent: Option<f64>,
+    
+    /// 计算平均 CPU 占用的窗口（秒）
+    #[serde(default = "default_cpu_window")]
+    pub cpu_window: u64,
+    
+    /// 看门狗：运行超过该时间（秒）后定期重启
+    #[serde(default)]
+    pub restart_after: Option<u64>,
+    
+    /// 依赖的进程，这些进程运行（配置了健康检查时需通过检查）后才启动本进程
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    
+    /// 启动优先级，没有依赖关系的进程按数值从小到大启动，停止时顺序相反
+    #[serde(default)]
+    pub priority: i32,
+    
+    /// 停止进程时发送的信号，例如 "SIGTERM"、"SIGQUIT"、"INT"
+    #[serde(default = "default_stop_signal")]
+    pub stop_signal: String,
+    
+    /// 发送停止信号后等待退出的时间（秒），超时后发送 SIGKILL
+    #[serde(default = "default_stop_timeout")]
+    pub stop_timeout: u64,
+    
+    /// 信号是否发送给整个进程组（含 Swoole worker 等子进程），为 false 时只发给主进程
+    #[serde(default = "default_kill_group")]
+    pub kill_group: bool,
+    
+    /// `m7d reload <name>` 发送的平滑重载信号，例如 Swoole 的 "SIGUSR1"
+    #[serde(default)]
+    pub reload_signal: Option<String>,
+}
+
+impl ProcessConfig {
+    /// Log file the captured output is written to, `None` when output is not captured
+    pub fn log_path(&self) -> Option<String> {
+        if !self.capture_output {
+            return None;
+        }
+        Some(self.log_file.clone().unwrap_or_else(|| format!("/var/log/mi7soft-{}.log", self.name)))
+    }
+    
+    /// Config of one instance: `{instance}` expanded in args and log_file, `M7D_INSTANCE` set
+    pub fn for_instance(&self, instance: usize) -> ProcessConfig {
+        let expand = |s: &String| s.replace("{instance}", &instance.to_string());
+        let mut config = self.clone();
+        config.args = self.args.iter().map(expand).collect();
+        config.log_file = self.log_file.as_ref().map(expand);
+        config.environment.insert("M7D_INSTANCE".to_string(), instance.to_string());
+        config
+    }
+}
+
+/// Split a CLI target such as "web:1" into the process name and instance index
+pub fn split_instance(target: &str) -> (&str, Option<usize>) {
+    match target.rsplit_once(':') {
+        Some((name, index)) => match index.parse() {
+            Ok(index) => (name, Some(index)),
+            Err(_) => (target, None),
+        },
+        None => (target, None),
+    }
+}
+
+fn default_instances() -> usize {
+    1
+}
+
+fn default_stop_signal() -> String {
+    "SIGTERM".to_string()
+}
+
+fn default_stop_timeout() -> u64 {
+    5
+}
+
+fn default_kill_group() -> bool {
+    true
 }
 
 fn default_capture_output() -> bool {
     true  // 默认捕获输出
 }
 
+fn default_max_log_files() -> usize {
+    5
+}
+
+fn default_restart_delay() -> u64 {
+    1
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+fn default_max_backoff() -> u64 {
+    60
+}
+
+fn default_max_restarts() -> u32 {
+    10
+}
+
+fn default_restart_window() -> u64 {
+    60
+}
+
+fn default_cpu_window() -> u64 {
+    60
+}
+
+fn default_min_uptime() -> u64 {
+    1
+}
+
 /// Load configuration from TOML file
 pub fn load_config(path: &Path) -> Result<DaemonConfig, DaemonError> {
     let content = std::fs::read_to_string(path)
@@ -117,7 +602,32 @@ fn validate_config(config: &DaemonConfig) -> Result<(), DaemonError> {
     if config.processes.is_empty() {
         return Err(DaemonError::Config("No processes defined".to_string()));
     }
+
+    if let Some(ref daemon) = config.daemon {
+        for (key, addr) in [("metrics_addr", &daemon.metrics_addr), ("api_addr", &daemon.api_addr)] {
+            if let Some(addr) = addr {
+                if addr.parse::<std::net::SocketAddr>().is_err() {
+                    return Err(DaemonError::Config(format!(
+                        "Invalid {} '{}', expected an address such as \"127.0.0.1:9101\"", key, addr
+                    )));
+                }
+            }
+        }
+        if daemon.api_addr.is_some() && daemon.api_token.as_deref().is_none_or(str::is_empty) {
+            return Err(DaemonError::Config("api_token is required when api_addr is set".to_string()));
+        }
+    }
+    
+    start_order(&config.processes)?;
     
+    for alert in &config.alerts {
+        if !alert.url.starts_with("http://") && !alert.url.starts_with("https://") {
+            return Err(DaemonError::Config(format!(
+                "Invalid alert url '{}', expected an http:// or https:// URL", alert.url
+            )));
+        }
+    }
+
     // Check for duplicate process names
     let mut names = std::collections::HashSet::new();
     for proc in &config.processes {
@@ -127,6 +637,66 @@ fn validate_config(config: &DaemonConfig) -> Result<(), DaemonError> {
             )));
         }
         
+        for dep in &proc.depends_on {
+            match config.processes.iter().find(|p| &p.name == dep) {
+                None => {
+                    return Err(DaemonError::Config(format!(
+                        "Process '{}' depends on unknown process '{}'", proc.name, dep
+                    )));
+                }
+                Some(p) if p.schedule.is_some() => {
+                    return Err(DaemonError::Config(format!(
+                        "Process '{}' cannot depend on scheduled process '{}'", proc.name, dep
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+        
+        if proc.instances == 0 {
+            return Err(DaemonError::Config(format!(
+                "Process '{}': instances must be at least 1", proc.name
+            )));
+        }
+        if proc.max_instances.is_some_and(|max| proc.instances > max) {
+            return Err(DaemonError::Config(format!(
+                "Process '{}': instances exceeds max_instances", proc.name
+            )));
+        }
+        if let Some(ref schedule) = proc.schedule {
+            schedule.validate().map_err(|e| DaemonError::Config(format!("Process '{}': {}", proc.name, e)))?;
+        }
+        // 各实例独立写入和轮转日志，共用同一个文件会在轮转时丢失或重复历史文件
+        if proc.instances > 1 && proc.log_path().is_some_and(|path| !path.contains("{instance}")) {
+            return Err(DaemonError::Config(format!(
+                "Process '{}': log_file must contain {{instance}} when instances > 1", proc.name
+            )));
+        }
+        if proc.instances > 1 && proc.schedule.is_some() {
+            return Err(DaemonError::Config(format!(
+                "Process '{}': scheduled processes run a single instance", proc.name
+            )));
+        }
+        
+        #[cfg(unix)]
+        for signal in std::iter::once(&proc.stop_signal).chain(proc.reload_signal.as_ref()) {
+            crate::signal::parse(signal).map_err(|_| DaemonError::Config(format!(
+                "Process '{}': unknown signal '{}'", proc.name, signal
+            )))?;
+        }
+        
+        // 验证运行用户/用户组存在
+        #[cfg(unix)]
+        crate::privileges::Credentials::resolve(proc)?;
+        
+        if let Some(cpu) = proc.limits.as_ref().and_then(|l| l.cpu_max) {
+            if cpu <= 0.0 || !cpu.is_finite() {
+                return Err(DaemonError::Config(format!(
+                    "Process '{}': limits.cpu_max must be a positive number of CPUs", proc.name
+                )));
+            }
+        }
+        
         // 验证日志文件路径
         if proc.capture_output && proc.log_file.is_none() {
             // 如果捕获输出但没有指定日志文件，使用默认路径
@@ -140,6 +710,177 @@ fn validate_config(config: &DaemonConfig) -> Result<(), DaemonError> {
     Ok(())
 }
 
+/// Check what can only be verified on this host: commands, working directories and log paths
+///
+/// Used by `m7d config check`; returns every problem instead of stopping at the first.
+pub fn check_environment(config: &DaemonConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    for proc in &config.processes {
+        let working_dir = proc.working_directory.as_deref().map(Path::new);
+        if let Some(dir) = working_dir {
+            if !dir.is_dir() {
+                problems.push(format!(
+                    "Process '{}': working_directory '{}' does not exist", proc.name, dir.display()
+                ));
+            }
+        }
+        if find_command(&proc.command, working_dir).is_none() {
+            problems.push(format!(
+                "Process '{}': command '{}' not found or not executable", proc.name, proc.command
+            ));
+        }
+        
+        // 多实例进程的日志路径可能各不相同
+        let log_paths: std::collections::BTreeSet<String> = (0..proc.instances)
+            .filter_map(|i| proc.for_instance(i).log_path())
+            .collect();
+        for path in log_paths {
+            if !is_writable(Path::new(&path)) {
+                problems.push(format!("Process '{}': log file '{}' is not writable", proc.name, path));
+            }
+        }
+    }
+    problems
+}
+
+/// Resolve a command like `Command::new` does: paths as given, bare names through `PATH`
+fn find_command(command: &str, working_dir: Option<&Path>) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if command.contains('/') {
+        let path = Path::new(command);
+        match working_dir {
+            Some(dir) if path.is_relative() => vec![dir.join(path)],
+            _ => vec![path.to_path_buf()],
+        }
+    } else {
+        std::env::var_os("PATH")
+            .map(|paths| std::env::split_paths(&paths).map(|dir| dir.join(command)).collect())
+            .unwrap_or_default()
+    };
+    candidates.into_iter().find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Whether the file can be appended to, or created under its nearest existing directory
+fn is_writable(path: &Path) -> bool {
+    if path.is_dir() {
+        return false;
+    }
+    // 日志目录不存在时会在启动进程时创建
+    let existing = path
+        .ancestors()
+        .map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p })
+        .find(|p| p.exists());
+    match existing {
+        #[cfg(unix)]
+        Some(p) => nix::unistd::access(p, nix::unistd::AccessFlags::W_OK).is_ok(),
+        #[cfg(not(unix))]
+        Some(p) => p.metadata().is_ok_and(|m| !m.permissions().readonly()),
+        None => false,
+    }
+}
+
+/// Order processes so that every process comes after its dependencies
+///
+/// Processes whose dependencies are satisfied start by ascending `priority`,
+/// then in file order. A dependency cycle is reported as a config error.
+pub fn start_order(processes: &[ProcessConfig]) -> Result<Vec<String>, DaemonError> {
+    let mut remaining: Vec<&ProcessConfig> = processes.iter().collect();
+    let mut order: Vec<String> = Vec::with_capacity(processes.len());
+    
+    while !remaining.is_empty() {
+        // 未知依赖由 validate_config 单独报告，这里视为已满足
+        let ready = |p: &ProcessConfig| {
+            p.depends_on.iter().all(|dep| order.contains(dep) || !processes.iter().any(|q| &q.name == dep))
+        };
+        let next = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| ready(p))
+            .min_by_key(|(_, p)| p.priority)
+            .map(|(i, _)| i);
+        match next {
+            Some(i) => order.push(remaining.remove(i).name.clone()),
+            None => return Err(DaemonError::Config(format!("Dependency cycle: {}", find_cycle(&remaining)))),
+        }
+    }
+    Ok(order)
+}
+
+/// Describe one cycle among processes that can never start, e.g. "a -> b -> a"
+fn find_cycle(blocked: &[&ProcessConfig]) -> String {
+    let mut path: Vec<&str> = Vec::new();
+    let mut current = blocked[0];
+    // 被阻塞的进程至少有一个依赖同样被阻塞，沿依赖走下去必然回到路径上的某个进程
+    loop {
+        if let Some(start) = path.iter().position(|name| *name == current.name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(&current.name);
+            return cycle.join(" -> ");
+        }
+        path.push(&current.name);
+        current = match current.depends_on.iter().find_map(|dep| blocked.iter().find(|p| &p.name == dep)) {
+            Some(next) => next,
+            None => return path.join(" -> "),
+        };
+    }
+}
+
+/// 两份配置之间的进程差异，用于重载配置
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigDiff {
+    /// 新增的进程
+    pub added: Vec<String>,
+    /// 被删除的进程
+    pub removed: Vec<String>,
+    /// 启动参数变化、需要重启的进程
+    pub changed: Vec<String>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare process lists by name; a process is changed when its command line or environment differs
+pub fn diff_processes(old: &[ProcessConfig], new: &[ProcessConfig]) -> ConfigDiff {
+    let mut diff = ConfigDiff::default();
+    
+    for new_proc in new {
+        match old.iter().find(|p| p.name == new_proc.name) {
+            None => diff.added.push(new_proc.name.clone()),
+            Some(old_proc) => {
+                if old_proc.command != new_proc.command
+                    || old_proc.args != new_proc.args
+                    || old_proc.environment != new_proc.environment
+                    || old_proc.working_directory != new_proc.working_directory
+                {
+                    diff.changed.push(new_proc.name.clone());
+                }
+            }
+        }
+    }
+    
+    for old_proc in old {
+        if !new.iter().any(|p| p.name == old_proc.name) {
+            diff.removed.push(old_proc.name.clone());
+        }
+    }
+    
+    diff
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,7 +909,7 @@ schedule = { type = "interval", interval = 5 }
 name = "another-process"
 command = "/bin/echo"
 args = ["hello"]
-schedule = { type = "cron", expression = "0 3 * * *" }
+schedule = { type = "cron", expression = "0 0 3 * * *" }
 "#;
         let mut temp_file = NamedTempFile::new().unwrap();
         write!(temp_file, "{}", config_content).unwrap();
@@ -182,6 +923,285 @@ schedule = { type = "cron", expression = "0 3 * * *" }
         assert!(config.processes[0].capture_output);
     }
 
+    #[test]
+    fn test_health_check_config() {
+        let config_content = r#"
+[[processes]]
+name = "web"
+command = "/usr/bin/php"
+
+[processes.health_check]
+type = "http"
+port = 8080
+path = "/health"
+interval = 5
+retries = 2
+start_period = 30
+
+[[processes]]
+name = "worker"
+command = "/usr/bin/php"
+health_check = { type = "file", path = "/tmp/worker.heartbeat", max_age = 60 }
+"#;
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{}", config_content).unwrap();
+        
+        let config = load_config(temp_file.path()).unwrap();
+        let web = config.processes[0].health_check.as_ref().unwrap();
+        assert_eq!(web.interval, 5);
+        assert_eq!(web.timeout, 5);
+        assert_eq!(web.retries, 2);
+        assert_eq!(web.start_period, 30);
+        assert_eq!(
+            web.probe,
+            HealthProbe::Http {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                path: "/health".to_string(),
+                expected_status: 200,
+            }
+        );
+        
+        let worker = config.processes[1].health_check.as_ref().unwrap();
+        assert!(matches!(worker.probe, HealthProbe::File { max_age: 60, .. }));
+    }
+
+    #[test]
+    fn test_diff_processes() {
+        let parse = |content: &str| toml::from_str::<DaemonConfig>(content).unwrap().processes;
+        let old = parse(r#"
+[[processes]]
+name = "same"
+command = "/bin/sleep"
+args = ["100"]
+
+[[processes]]
+name = "args"
+command = "/bin/sleep"
+args = ["100"]
+
+[[processes]]
+name = "env"
+command = "/bin/sleep"
+environment = { A = "1" }
+
+[[processes]]
+name = "gone"
+command = "/bin/sleep"
+"#);
+        let new = parse(r#"
+[[processes]]
+name = "same"
+command = "/bin/sleep"
+args = ["100"]
+auto_restart = true
+
+[[processes]]
+name = "args"
+command = "/bin/sleep"
+args = ["200"]
+
+[[processes]]
+name = "env"
+command = "/bin/sleep"
+environment = { A = "2" }
+
+[[processes]]
+name = "new"
+command = "/bin/sleep"
+"#);
+        
+        let diff = diff_processes(&old, &new);
+        assert_eq!(diff.added, vec!["new"]);
+        assert_eq!(diff.removed, vec!["gone"]);
+        assert_eq!(diff.changed, vec!["args", "env"]);
+        assert!(diff_processes(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_start_order_and_cycles() {
+        let parse = |content: &str| toml::from_str::<DaemonConfig>(content).unwrap();
+        let config = parse(r#"
+[[processes]]
+name = "web"
+command = "/usr/bin/php"
+depends_on = ["redis-proxy", "queue"]
+
+[[processes]]
+name = "queue"
+command = "/bin/sleep"
+priority = 10
+
+[[processes]]
+name = "metrics"
+command = "/bin/sleep"
+priority = 5
+
+[[processes]]
+name = "redis-proxy"
+command = "/bin/sleep"
+priority = 20
+"#);
+        assert_eq!(start_order(&config.processes).unwrap(), vec!["metrics", "queue", "redis-proxy", "web"]);
+        assert!(validate_config(&config).is_ok());
+
+        let cyclic = parse(r#"
+[[processes]]
+name = "a"
+command = "/bin/sleep"
+depends_on = ["b"]
+
+[[processes]]
+name = "b"
+command = "/bin/sleep"
+depends_on = ["c"]
+
+[[processes]]
+name = "c"
+command = "/bin/sleep"
+depends_on = ["b"]
+"#);
+        let err = validate_config(&cyclic).unwrap_err().to_string();
+        assert!(err.contains("Dependency cycle: b -> c -> b"), "{}", err);
+
+        let unknown = parse("[[processes]]\nname = \"a\"\ncommand = \"/bin/sleep\"\ndepends_on = [\"nope\"]\n");
+        assert!(validate_config(&unknown).unwrap_err().to_string().contains("unknown process 'nope'"));
+    }
+
+    #[test]
+    fn test_instances() {
+        let parse = |content: &str| toml::from_str::<DaemonConfig>(content).unwrap();
+        let config = parse(r#"
+[[processes]]
+name = "worker"
+command = "/usr/bin/php"
+args = ["worker.php", "--id={instance}"]
+log_file = "/var/log/worker-{instance}.log"
+instances = 4
+"#);
+        validate_config(&config).unwrap();
+        let worker = config.processes[0].for_instance(2);
+        assert_eq!(worker.args, vec!["worker.php", "--id=2"]);
+        assert_eq!(worker.log_path().unwrap(), "/var/log/worker-2.log");
+        assert_eq!(worker.environment["M7D_INSTANCE"], "2");
+        
+        assert_eq!(split_instance("worker:3"), ("worker", Some(3)));
+        assert_eq!(split_instance("worker"), ("worker", None));
+        assert_eq!(split_instance("a:b"), ("a:b", None));
+        
+        for invalid in [
+            "instances = 0",
+            "instances = 3\nmax_instances = 2",
+            "instances = 2\nlog_file = \"/tmp/w-{instance}.log\"\nschedule = { type = \"interval\", interval = 60 }",
+            "instances = 2",
+            "instances = 2\nlog_file = \"/var/log/worker.log\"",
+        ] {
+            let config = parse(&format!("[[processes]]\nname = \"worker\"\ncommand = \"/bin/true\"\n{}", invalid));
+            assert!(validate_config(&config).is_err(), "{}", invalid);
+        }
+        
+        // 不捕获输出时没有共享的日志文件
+        let config = parse("[[processes]]\nname = \"worker\"\ncommand = \"/bin/true\"\ninstances = 2\ncapture_output = false");
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    fn test_stop_settings() {
+        let parse = |content: &str| toml::from_str::<DaemonConfig>(content).unwrap();
+        let config = parse("[[processes]]\nname = \"web\"\ncommand = \"/bin/true\"");
+        let web = &config.processes[0];
+        assert_eq!((web.stop_signal.as_str(), web.stop_timeout, web.kill_group), ("SIGTERM", 5, true));
+        assert!(web.reload_signal.is_none());
+        
+        let config = parse("[[processes]]\nname = \"web\"\ncommand = \"/bin/true\"\nstop_signal = \"QUIT\"\nreload_signal = \"SIGUSR1\"");
+        validate_config(&config).unwrap();
+        let config = parse("[[processes]]\nname = \"web\"\ncommand = \"/bin/true\"\nreload_signal = \"SIGRELOAD\"");
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_schedule_options() {
+        let parse = |schedule: &str| {
+            toml::from_str::<DaemonConfig>(&format!(
+                "[[processes]]\nname = \"job\"\ncommand = \"/bin/true\"\nschedule = {{ {} }}", schedule
+            ))
+            .unwrap()
+        };
+        let config = parse("type = \"cron\", expression = \"0 0 3 * * *\", timezone = \"Asia/Shanghai\", concurrency_policy = \"forbid\", starting_deadline = 3600");
+        validate_config(&config).unwrap();
+        let schedule = config.processes[0].schedule.as_ref().unwrap();
+        assert_eq!(schedule.tz().unwrap(), chrono_tz::Asia::Shanghai);
+        assert_eq!(schedule.concurrency_policy, ConcurrencyPolicy::Forbid);
+        assert_eq!(schedule.starting_deadline, Some(3600));
+        
+        let config = parse("type = \"interval\", interval = 60");
+        let schedule = config.processes[0].schedule.as_ref().unwrap();
+        assert_eq!((schedule.tz().unwrap(), schedule.concurrency_policy), (chrono_tz::UTC, ConcurrencyPolicy::Allow));
+        
+        assert!(validate_config(&parse("type = \"cron\", expression = \"0 0 3 * * *\", timezone = \"Mars/Olympus\"")).is_err());
+    }
+
+    #[test]
+    fn test_schedule_validation() {
+        let validate = |schedule: &str| {
+            let config = toml::from_str::<DaemonConfig>(&format!(
+                "[[processes]]\nname = \"job\"\ncommand = \"/bin/true\"\nschedule = {{ {} }}", schedule
+            ))
+            .unwrap();
+            validate_config(&config).map_err(|e| e.to_string())
+        };
+        validate("type = \"cron\", expression = \"0 */5 * * * *\", timeout = 60").unwrap();
+        validate("type = \"interval\", interval = 30").unwrap();
+        validate("type = \"cron\", expression = \"H H(0-5) 3 * * *\", jitter = 300").unwrap();
+        validate("type = \"cron\", expression = \"0 0 3 * * THU\"").unwrap();
+        validate("type = \"cron\", expression = \"H 0 3 * * THU-SAT\"").unwrap();
+        
+        for (schedule, message) in [
+            ("type = \"cron\"", "cron schedule requires 'expression'"),
+            ("type = \"cron\", expression = \"0 3 * * *\"", "expected 6 or 7 fields (sec min hour day month weekday [year]), got 5"),
+            ("type = \"cron\", expression = \"0 0 25 * * *\"", "Hours must be less than 23"),
+            ("type = \"cron\", expression = \"0 0 3 * * *\", interval = 60", "'interval' is not used"),
+            ("type = \"interval\"", "interval schedule requires 'interval'"),
+            ("type = \"interval\", interval = 0", "'interval' must be greater than 0"),
+            ("type = \"interval\", interval = 60, expression = \"0 * * * * *\"", "'expression' is not used"),
+            ("type = \"interval\", interval = 60, timezone = \"UTC\"", "'timezone' only applies"),
+            ("type = \"interval\", interval = 60, timeout = 0", "'timeout' must be greater than 0"),
+            ("type = \"interval\", interval = 60, jitter = 0", "'jitter' must be greater than 0"),
+            ("type = \"cron\", expression = \"0 H(5-1) 3 * * *\"", "invalid H token 'H(5-1)'"),
+        ] {
+            let err = validate(schedule).unwrap_err();
+            assert!(err.contains("Process 'job': ") && err.contains(message), "{}: {}", schedule, err);
+        }
+    }
+
+    #[test]
+    fn test_check_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = toml::from_str::<DaemonConfig>(&format!(
+            r#"
+[[processes]]
+name = "ok"
+command = "sh"
+log_file = "{dir}/logs/ok.log"
+
+[[processes]]
+name = "broken"
+command = "/nonexistent/bin/app"
+working_directory = "{dir}/missing"
+log_file = "{dir}"
+"#,
+            dir = dir.path().display()
+        ))
+        .unwrap();
+        
+        let problems = check_environment(&config);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems.iter().all(|p| p.starts_with("Process 'broken': ")));
+        assert!(problems[0].contains("working_directory"));
+        assert!(problems[1].contains("command '/nonexistent/bin/app'"));
+        assert!(problems[2].contains("not writable"));
+    }
+
     #[test]
     fn test_invalid_toml() {
         let invalid_content = r#"
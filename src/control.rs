@@ -0,0 +1,347 @@
+//! 控制套接字模块
+//!
+//! 运行中的守护进程在 Unix 域套接字上监听控制请求，CLI 通过它与守护进程通信，
+//! 而不是重新构建一个 `Daemon`。协议为单行 JSON：客户端发送一个请求行，
+//! 守护进程回复一个响应行，双方都带有协议版本号。
+
+use crate::error::DaemonError;
+use crate::process::{ProcessStatus, ScheduleInfo};
+use crate::storage::ProcessHistory;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+
+/// 控制协议版本，请求与响应不兼容时递增
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Derive the control socket path from the PID file path
+pub fn socket_path(pid_file_path: &str) -> PathBuf {
+    PathBuf::from(pid_file_path).with_extension("sock")
+}
+
+/// 控制命令
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    Start { name: String },
+    Stop { name: String },
+    Restart { name: String },
+    Status { name: Option<String> },
+    Reload,
+    Shutdown,
+    Metrics,
+    History { name: String, limit: usize },
+    LogFile { name: String },
+    ReloadProcess { name: String },
+    Schedules { count: usize },
+    RunJob { name: String },
+}
+
+/// 控制响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ControlResponse {
+    Started { pid: u32 },
+    Stopped { pids: Vec<u32> },
+    Restarted { pids: Vec<u32> },
+    Status { processes: Vec<ProcessStatus> },
+    Reloaded,
+    ShuttingDown,
+    Metrics { text: String },
+    History { records: Vec<ProcessHistory> },
+    LogFile { path: String },
+    ProcessReloaded { pids: Vec<u32> },
+    Schedules { schedules: Vec<ScheduleInfo> },
+    Error { message: String, exit_code: i32 },
+}
+
+impl ControlResponse {
+    /// Convert a daemon-side error into a response the client can re-raise
+    pub fn from_error(err: &DaemonError) -> Self {
+        ControlResponse::Error {
+            message: err.to_string(),
+            exit_code: err.exit_code(),
+        }
+    }
+
+    /// Turn an error response back into a `DaemonError` on the client side
+    pub fn into_result(self) -> Result<Self, DaemonError> {
+        match self {
+            ControlResponse::Error { message, exit_code } => {
+                Err(DaemonError::Remote { message, exit_code })
+            }
+            other => Ok(other),
+        }
+    }
+}
+
+/// 线上的请求格式
+#[derive(Debug, Serialize, Deserialize)]
+struct RequestFrame {
+    version: u32,
+    #[serde(flatten)]
+    command: ControlCommand,
+}
+
+/// 线上的响应格式
+#[derive(Debug, Serialize, Deserialize)]
+struct ResponseFrame {
+    version: u32,
+    #[serde(flatten)]
+    response: ControlResponse,
+}
+
+/// 等待守护进程主循环处理的请求
+pub struct PendingRequest {
+    pub command: ControlCommand,
+    reply: oneshot::Sender<ControlResponse>,
+}
+
+impl PendingRequest {
+    /// Send the response back to the waiting client
+    pub fn respond(self, response: ControlResponse) {
+        let _ = self.reply.send(response);
+    }
+}
+
+/// Hand a command to the daemon main loop and wait for its response
+///
+/// Returns `None` when the main loop has gone away.
+pub async fn dispatch(
+    sender: &mpsc::UnboundedSender<PendingRequest>,
+    command: ControlCommand,
+) -> Option<ControlResponse> {
+    let (reply, reply_rx) = oneshot::channel();
+    sender.send(PendingRequest { command, reply }).ok()?;
+    reply_rx.await.ok()
+}
+
+/// 控制套接字服务端
+///
+/// 请求通过通道转交给 `Daemon::run` 主循环处理，因此进程注册表只会被主循环修改。
+/// 服务端被 drop 时删除套接字文件。
+pub struct ControlServer {
+    path: PathBuf,
+    sender: mpsc::UnboundedSender<PendingRequest>,
+}
+
+impl ControlServer {
+    /// Bind the socket and start accepting connections
+    pub fn bind(path: &Path) -> Result<(Self, mpsc::UnboundedReceiver<PendingRequest>), DaemonError> {
+        // PID 文件锁已保证只有一个守护进程，残留的套接字可以直接删除
+        if path.exists() {
+            std::fs::remove_file(path)
+                .map_err(|e| DaemonError::Control(format!("Failed to remove stale socket: {}", e)))?;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+
+        let listener = UnixListener::bind(path)
+            .map_err(|e| DaemonError::Control(format!("Failed to bind {}: {}", path.display(), e)))?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn({
+            let sender = sender.clone();
+            async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let sender = sender.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = handle_connection(stream, sender).await {
+                                    debug!(error = %e, "Control connection closed with error");
+                                }
+                            });
+                        }
+                        Err(e) => {
+                            error!(error = %e, "Failed to accept control connection");
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        info!(path = ?path, "Control socket listening");
+        Ok((Self { path: path.to_path_buf(), sender }, receiver))
+    }
+
+    /// Sender for the HTTP front ends (metrics, management API) to reach the main loop
+    pub fn requests(&self) -> mpsc::UnboundedSender<PendingRequest> {
+        self.sender.clone()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    sender: mpsc::UnboundedSender<PendingRequest>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let line = match lines.next_line().await? {
+        Some(line) => line,
+        None => return Ok(()),
+    };
+
+    let response = match serde_json::from_str::<RequestFrame>(&line) {
+        Ok(frame) if frame.version != PROTOCOL_VERSION => ControlResponse::Error {
+            message: format!(
+                "Unsupported protocol version {} (daemon speaks {})",
+                frame.version, PROTOCOL_VERSION
+            ),
+            exit_code: 76, // EX_PROTOCOL
+        },
+        Ok(frame) => match dispatch(&sender, frame.command).await {
+            Some(response) => response,
+            None => return Ok(()),
+        },
+        Err(e) => {
+            warn!(error = %e, "Invalid control request");
+            ControlResponse::Error {
+                message: format!("Invalid request: {}", e),
+                exit_code: 76,
+            }
+        }
+    };
+
+    let frame = ResponseFrame { version: PROTOCOL_VERSION, response };
+    let mut payload = serde_json::to_string(&frame)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    payload.push('\n');
+    writer.write_all(payload.as_bytes()).await?;
+    writer.shutdown().await
+}
+
+/// Send a command to the running daemon
+///
+/// Returns `Ok(None)` when no daemon is listening on the socket, so the caller
+/// can fall back to operating on the state file directly.
+pub async fn send_command(
+    path: &Path,
+    command: ControlCommand,
+) -> Result<Option<ControlResponse>, DaemonError> {
+    let stream = match UnixStream::connect(path).await {
+        Ok(s) => s,
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            debug!(path = ?path, "No daemon listening on control socket");
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(DaemonError::Control(format!(
+                "Failed to connect to {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+
+    let (reader, mut writer) = stream.into_split();
+    let frame = RequestFrame { version: PROTOCOL_VERSION, command };
+    let mut payload = serde_json::to_string(&frame)
+        .map_err(|e| DaemonError::Control(format!("Failed to encode request: {}", e)))?;
+    payload.push('\n');
+    writer.write_all(payload.as_bytes()).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| DaemonError::Control("Daemon closed the connection".to_string()))?;
+
+    let frame: ResponseFrame = serde_json::from_str(&line)
+        .map_err(|e| DaemonError::Control(format!("Invalid response: {}", e)))?;
+
+    if frame.version != PROTOCOL_VERSION {
+        return Err(DaemonError::Control(format!(
+            "Daemon speaks protocol version {}, expected {}",
+            frame.version, PROTOCOL_VERSION
+        )));
+    }
+
+    Ok(Some(frame.response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("daemon.sock");
+
+        let (_server, mut receiver) = ControlServer::bind(&path).unwrap();
+
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let response = match &request.command {
+                    ControlCommand::Stop { name } if name == "web" => {
+                        ControlResponse::Stopped { pids: vec![42] }
+                    }
+                    _ => ControlResponse::from_error(&DaemonError::NotRunning {
+                        name: "other".to_string(),
+                    }),
+                };
+                request.respond(response);
+            }
+        });
+
+        let response = send_command(&path, ControlCommand::Stop { name: "web".to_string() })
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(response, ControlResponse::Stopped { ref pids } if pids == &[42]));
+
+        let response = send_command(&path, ControlCommand::Reload)
+            .await
+            .unwrap()
+            .unwrap();
+        let err = response.into_result().unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_daemon_listening() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("missing.sock");
+
+        let response = send_command(&path, ControlCommand::Shutdown).await.unwrap();
+        assert!(response.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_version_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("daemon.sock");
+        let (_server, _receiver) = ControlServer::bind(&path).unwrap();
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"{\"version\":999,\"command\":\"reload\"}\n")
+            .await
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).await.unwrap();
+
+        let frame: ResponseFrame = serde_json::from_str(&line).unwrap();
+        assert!(matches!(frame.response, ControlResponse::Error { exit_code: 76, .. }));
+    }
+}
@@ -1,23 +1,51 @@
-use crate::config::{DaemonConfig, ProcessConfig, load_config};
+use crate::config::{self, AlertEvent, ConcurrencyPolicy, DaemonConfig, ProcessConfig, load_config};
+use crate::control::{self, ControlCommand, ControlResponse, ControlServer, PendingRequest};
 use crate::error::DaemonError;
-use crate::process::{ProcessManager, ProcessStatus, Scheduler};
+use crate::health::{self, HealthState};
+use crate::alert::{Alert, Alerter};
+use crate::api;
+use crate::metrics::{self, ProcessMetrics};
+use crate::process::{ProcessEvent, ProcessManager, ProcessState, ProcessStatus, ScheduleInfo, Scheduler};
+use crate::restart::{RestartDecision, RestartPolicy, RestartTracker};
 use crate::pidfile::PidFile;
 use crate::signal::{Signal, SignalHandler};
-use std::collections::HashMap;
+use crate::storage::{self, Storage};
+use crate::procfs;
+use crate::watchdog::{CpuSampler, Watchdog};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 pub struct Daemon {
     config_path: PathBuf,
     state_file: PathBuf,
+    socket_path: PathBuf,
     config: DaemonConfig,
     process_manager: ProcessManager,
     pid_file: PidFile,
     signal_handler: SignalHandler,
     shutdown_tx: Option<mpsc::UnboundedSender<bool>>,
     schedulers: HashMap<String, Scheduler>,
+    storage: Option<Storage>,
+    restart_trackers: HashMap<String, RestartTracker>,
+    /// 按 (进程, 实例序号) 记录的健康检查状态
+    health: HashMap<(String, usize), HealthState>,
+    cpu_samplers: HashMap<u32, CpuSampler>,
+    restart_counts: HashMap<String, u32>,
+    /// 每个进程最近一次退出的退出码，导出为指标
+    last_exit_codes: HashMap<String, i32>,
+    alerter: Alerter,
+    /// 按启动顺序排列、等待依赖就绪的进程及启动原因
+    pending_starts: Vec<(String, &'static str)>,
+    /// 等待自动重启的实例序号
+    crashed_instances: HashMap<String, Vec<usize>>,
+    /// 因超过调度 timeout 被终止、尚未上报退出的调度任务 PID
+    timed_out: HashSet<u32>,
+    /// 上一次按 check_interval 完整检查进程存活的时间
+    last_full_check: Instant,
 }
 
 impl Daemon {
@@ -34,8 +62,12 @@ impl Daemon {
         let mut process_manager = ProcessManager::new();
         
         let state_file = PathBuf::from(pid_file_path).with_extension("state");
+        let socket_path = control::socket_path(pid_file_path);
         
         process_manager.load_state(&state_file)?;
+        if let Some(ref settings) = config.daemon {
+            process_manager.set_cgroup_parent(&settings.cgroup_parent);
+        }
         
         let mut pid_file = PidFile::new(pid_file_path);
         
@@ -47,33 +79,54 @@ impl Daemon {
             let _ = pid_file.acquire_lock;
         }
 
-        let global_interval = config.daemon.as_ref()
-            .map(|d| d.check_interval)
-            .unwrap_or(5);
-
-        let mut schedulers = HashMap::new();
-        for proc in &config.processes {
-            if let Some(ref schedule) = proc.schedule {
-                let scheduler = Scheduler::from_config(schedule, global_interval);
-                schedulers.insert(proc.name.clone(), scheduler);
+        let schedulers = build_schedulers(&config);
+        let alerter = Alerter::new(&config.alerts);
+        
+        // 只读模式（离线查看状态）不写历史记录
+        let storage = if acquire_lock {
+            match Storage::new(storage::default_db_path()) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!(error = %e, "Failed to open history database, history will not be recorded");
+                    None
+                }
             }
-        }
+        } else {
+            None
+        };
         
         Ok(Self {
             config_path,
             state_file,
+            socket_path,
             config,
             process_manager,
             pid_file,
             signal_handler: SignalHandler::new(),
             shutdown_tx: None,
             schedulers,
+            storage,
+            restart_trackers: HashMap::new(),
+            health: HashMap::new(),
+            cpu_samplers: HashMap::new(),
+            restart_counts: HashMap::new(),
+            last_exit_codes: HashMap::new(),
+            alerter,
+            pending_starts: Vec::new(),
+            crashed_instances: HashMap::new(),
+            timed_out: HashSet::new(),
+            last_full_check: Instant::now(),
         })
     }
     
     pub async fn run(&mut self) -> Result<(), DaemonError> {
         info!("Starting daemon");
         
+        let retention_days = self.config.daemon.as_ref()
+            .map(|d| d.history_retention_days)
+            .unwrap_or(30);
+        self.record(|s| s.cleanup_old_records(retention_days).map(|_| ()));
+        
         info!("Checking for existing processes...");
         if self.process_manager.process_names().is_empty() {
             info!("No processes found, spawning from config...");
@@ -83,19 +136,38 @@ impl Daemon {
         }
         
         self.process_manager.save_state(&self.state_file)?;
+        self.catch_up_missed_runs();
         
         let (shutdown_tx, mut shutdown_rx) = mpsc::unbounded_channel();
         self.shutdown_tx = Some(shutdown_tx);
         
+        // 控制套接字在主循环退出时随 _control_server 一起清理
+        let (control_server, mut control_rx) = ControlServer::bind(&self.socket_path)?;
+        
+        // HTTP 监听不可用时只记录错误，不影响守护进程运行
+        if let Some(addr) = self.config.daemon.as_ref().and_then(|d| d.metrics_addr.clone()) {
+            if let Err(e) = metrics::serve(&addr, control_server.requests()).await {
+                error!(error = %e, "Failed to start metrics listener");
+            }
+        }
+        if let Some(ref settings) = self.config.daemon {
+            if let Some(ref addr) = settings.api_addr {
+                // validate_config 保证配置了 api_addr 时 api_token 非空
+                let token = settings.api_token.clone().unwrap_or_default();
+                if let Err(e) = api::serve(addr, token, control_server.requests()).await {
+                    error!(error = %e, "Failed to start HTTP API");
+                }
+            }
+        }
+        
+        let mut process_events = self.process_manager.take_events()
+            .ok_or_else(|| DaemonError::Config("Process event receiver already taken".to_string()))?;
+        
         let global_interval = self.config.daemon.as_ref()
             .map(|d| d.check_interval)
             .unwrap_or(5);
         
-        let has_schedulers = !self.schedulers.is_empty();
-        
-        let has_schedulers = !self.schedulers.is_empty();
-        
-        if has_schedulers {
+        if !self.schedulers.is_empty() {
             info!(count = self.schedulers.len(), "Using per-process scheduling");
             for (name, sched) in &self.schedulers {
             info!(process = name.as_str(), scheduler_type = ?sched.scheduler_type, next_run = ?sched.next_run, "Scheduler initialized");
@@ -108,18 +180,24 @@ impl Daemon {
             tokio::time::Duration::from_secs(1)
         );
         
-        let mut last_full_check = Instant::now();
+        self.last_full_check = Instant::now();
         
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    if has_schedulers {
+                    self.run_pending_starts().await;
+                    self.run_pending_restarts().await;
+                    self.run_health_checks();
+                    self.run_watchdog().await;
+                    self.enforce_job_timeouts().await;
+                    // 重载配置可能增删调度进程，每次重新判断
+                    if !self.schedulers.is_empty() {
                         self.monitor_scheduled().await?;
                     } else {
-                        if last_full_check.elapsed() >= tokio::time::Duration::from_secs(global_interval) {
+                        if self.last_full_check.elapsed() >= tokio::time::Duration::from_secs(global_interval) {
                             self.monitor_and_restart().await?;
                             self.process_manager.save_state(&self.state_file)?;
-                            last_full_check = Instant::now();
+                            self.last_full_check = Instant::now();
                         }
                     }
                 }
@@ -132,11 +210,21 @@ impl Daemon {
                         }
                         Signal::ReloadConfig => {
                             info!("Received reload config signal");
-                            self.reload_config()?;
+                            // 重载失败时保留原配置继续运行
+                            let _ = self.reload_config().await;
+                        }
+                        Signal::ReopenLogs => {
+                            info!("Received reopen logs signal");
+                            self.process_manager.reopen_logs();
                         }
-                        _ => {}
                     }
                 }
+                Some(event) = process_events.recv() => {
+                    self.handle_process_event(event).await?;
+                }
+                Some(request) = control_rx.recv() => {
+                    self.handle_control(request).await;
+                }
                 _ = shutdown_rx.recv() => {
                     info!("Received shutdown request");
                     self.shutdown().await?;
@@ -151,21 +239,18 @@ impl Daemon {
             .map(|d| d.check_interval)
             .unwrap_or(5);
 
-        static LAST_FULL_CHECK: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
-        let last_check = LAST_FULL_CHECK.get_or_init(Instant::now);
-
         // Get scheduler names to check
         let scheduler_names: Vec<String> = self.schedulers.keys().cloned().collect();
         
         for name in scheduler_names {
             if let Some(scheduler) = self.schedulers.get_mut(&name) {
                 let should_run = scheduler.should_run();
-                info!(process = name.as_str(), should_run = should_run, "Cron check");
+                debug!(process = name.as_str(), should_run = should_run, "Cron check");
                 if should_run {
+                    let slot = scheduler.last_slot;
                     if let Some(config) = self.find_config(&name) {
                         let config = config.clone();
-                        // 启动 cron 进程
-                        if let Err(e) = self.process_manager.spawn(&config).await {
+                        if let Err(e) = self.launch_job(&config, slot, "schedule").await {
                             error!(process = name.as_str(), error = %e, "Failed to start cron process");
                         }
                     }
@@ -174,92 +259,590 @@ impl Daemon {
         }
 
         // 清理已死亡的 cron 进程（但不自动重启，由 cron 调度控制）
-        let dead_names = self.process_manager.cleanup_dead();
-        if !dead_names.is_empty() {
+        let dead = self.process_manager.cleanup_dead();
+        if !dead.is_empty() {
+            let dead_names: Vec<&str> = dead.iter().map(|e| e.name.as_str()).collect();
             info!(processes = ?dead_names, "Cron processes completed");
+            for entry in &dead {
+                self.record_end(&entry.name, entry.pid, entry.uptime(), None, "dead");
+            }
         }
 
-        if last_check.elapsed() >= tokio::time::Duration::from_secs(global_interval) {
+        if self.last_full_check.elapsed() >= tokio::time::Duration::from_secs(global_interval) {
             self.monitor_and_restart().await?;
-            // Reset the instant
-            let _ = LAST_FULL_CHECK.set(Instant::now());
+            self.last_full_check = Instant::now();
         }
 
         self.process_manager.save_state(&self.state_file)?;
         Ok(())
     }
 
-    async fn monitor_single(&mut self, name: &str) -> Result<(), DaemonError> {
-        // 非 cron 进程：死亡后自动重启
-        // cron 进程：不做处理，由 monitor_scheduled 控制
-        let config = match self.find_config(name) {
-            Some(c) if c.schedule.is_none() && c.auto_restart => c.clone(),
-            _ => return Ok(()),
+    /// Start one run of a scheduled process, applying its concurrency policy
+    ///
+    /// `slot` is the scheduled time of the run, `None` for manual runs. Returns
+    /// `None` when the run was skipped because the previous one is still active.
+    async fn launch_job(&mut self, config: &ProcessConfig, slot: Option<DateTime<Utc>>, reason: &'static str) -> Result<Option<u32>, DaemonError> {
+        let name = config.name.as_str();
+        let policy = config.schedule.as_ref().map(|s| s.concurrency_policy).unwrap_or_default();
+        if !self.process_manager.entries(name).is_empty() {
+            match policy {
+                ConcurrencyPolicy::Allow => {}
+                ConcurrencyPolicy::Forbid => {
+                    warn!(process = name, "Previous run still active, skipping scheduled run");
+                    return Ok(None);
+                }
+                ConcurrencyPolicy::Replace => {
+                    warn!(process = name, "Previous run still active, replacing it");
+                    if let Err(e) = self.stop_and_record(name, "replaced").await {
+                        error!(process = name, error = %e, "Failed to stop previous run");
+                    }
+                }
+            }
+        }
+        
+        let pid = self.spawn_process(config, None, reason).await?;
+        if let Some(scheduler) = self.schedulers.get_mut(name) {
+            scheduler.stats.runs += 1;
+        }
+        self.record(|s| s.record_job_start(name, pid, slot).map(|_| ()));
+        Ok(Some(pid))
+    }
+    
+    /// Trigger an out-of-band run of a scheduled process
+    pub async fn run_job(&mut self, name: &str) -> Result<u32, DaemonError> {
+        let config = self.find_config(name)
+            .filter(|c| c.schedule.is_some())
+            .ok_or_else(|| DaemonError::Config(format!("Process '{}' is not a scheduled process", name)))?
+            .clone();
+        info!(process = name, "Manual run requested");
+        match self.launch_job(&config, None, "manual").await? {
+            Some(pid) => Ok(pid),
+            None => {
+                let pid = self.process_manager.entries(name).first().map(|e| e.pid).unwrap_or(0);
+                Err(DaemonError::AlreadyRunning { name: name.to_string(), pid })
+            }
+        }
+    }
+    
+    /// Upcoming fire times and last result of every scheduled process
+    pub fn schedules(&self, count: usize) -> Vec<ScheduleInfo> {
+        self.config.processes
+            .iter()
+            .filter_map(|config| {
+                let schedule = config.schedule.clone()?;
+                let scheduler = self.schedulers.get(&config.name)?;
+                let last_run = self.storage.as_ref()
+                    .and_then(|s| s.get_job_runs(&config.name, 1).ok())
+                    .and_then(|runs| runs.into_iter().next());
+                Some(ScheduleInfo {
+                    name: config.name.clone(),
+                    resolved_expression: scheduler.resolved_expression.clone(),
+                    upcoming: scheduler.upcoming(count),
+                    next_delay: schedule.jitter.map(|_| scheduler.next_delay().as_secs()),
+                    schedule,
+                    last_run,
+                })
+            })
+            .collect()
+    }
+    
+    /// Run scheduled jobs whose last slot was missed while the daemon was down
+    ///
+    /// Only jobs with `starting_deadline` are caught up, and only once even if
+    /// several slots were missed. The last run comes from the history database.
+    fn catch_up_missed_runs(&mut self) {
+        let storage = match self.storage {
+            Some(ref storage) => storage,
+            None => return,
+        };
+        let now = Utc::now();
+        for config in &self.config.processes {
+            let deadline = match config.schedule.as_ref().and_then(|s| s.starting_deadline) {
+                Some(deadline) => Duration::from_secs(deadline),
+                None => continue,
+            };
+            let last_run = match storage.get_stats(&config.name) {
+                Ok(stats) => stats.and_then(|s| s.last_start_time),
+                Err(e) => {
+                    warn!(process = config.name.as_str(), error = %e, "Failed to read last run");
+                    continue;
+                }
+            };
+            let (last_run, scheduler) = match (last_run, self.schedulers.get_mut(&config.name)) {
+                (Some(last_run), Some(scheduler)) => (last_run, scheduler),
+                _ => continue,
+            };
+            if let Some(missed) = scheduler.missed_run(last_run, now, deadline) {
+                info!(process = config.name.as_str(), %last_run, %missed, "Catching up missed scheduled run");
+                scheduler.run_now(missed);
+            }
+        }
+    }
+    
+    /// Stop scheduled runs that have been running longer than their `timeout`
+    ///
+    /// The run is only signalled here; its exit is reported by the watcher and
+    /// recorded as timed out in `handle_process_event`.
+    async fn enforce_job_timeouts(&mut self) {
+        let mut overdue = Vec::new();
+        for config in &self.config.processes {
+            let timeout = match config.schedule.as_ref().and_then(|s| s.timeout) {
+                Some(timeout) => timeout,
+                None => continue,
+            };
+            for entry in self.process_manager.entries(&config.name) {
+                if entry.uptime() >= timeout && !self.timed_out.contains(&entry.pid) {
+                    overdue.push((entry.pid, entry.config.clone(), timeout));
+                }
+            }
+        }
+        
+        for (pid, config, timeout) in overdue {
+            warn!(process = config.name.as_str(), pid = pid, timeout = timeout, "Scheduled run timed out, stopping it");
+            self.timed_out.insert(pid);
+            if let Err(e) = self.process_manager.stop_by_pid(pid, &config).await {
+                error!(process = config.name.as_str(), pid = pid, error = %e, "Failed to stop timed out run");
+            }
+        }
+    }
+    
+    /// React to an event reported by a watcher or health probe task
+    async fn handle_process_event(&mut self, event: ProcessEvent) -> Result<(), DaemonError> {
+        let (name, pid, exit) = match event {
+            ProcessEvent::Exited { name, pid, exit } => (name, pid, exit),
+            ProcessEvent::HealthChecked { name, pid, result } => {
+                return self.handle_health_result(name, pid, result).await;
+            }
         };
         
-        let all_dead = self.process_manager.cleanup_dead();
-        if all_dead.contains(&name.to_string()) {
-            warn!(process = name, "Auto-restarting dead process");
-            if let Err(e) = self.process_manager.spawn(&config).await {
-                error!(process = name, error = %e, "Failed to restart process");
+        // 已经被 stop/restart 移除的进程属于预期退出，无需处理
+        let entry = match self.process_manager.remove_exited(&name, pid) {
+            Some(entry) => entry,
+            None => {
+                debug!(process = name.as_str(), pid = pid, %exit, "Stopped process exited");
+                return Ok(());
             }
+        };
+        
+        // 被信号终止时按 shell 约定记录为 128 + signal
+        let exit_code = exit.code.or(exit.signal.map(|sig| 128 + sig));
+        let timed_out = self.timed_out.contains(&pid);
+        self.health.remove(&(name.clone(), entry.instance));
+        self.record_end(&name, pid, entry.uptime(), exit_code, &exit.to_string());
+        if let Some(code) = exit_code {
+            self.last_exit_codes.insert(name.clone(), code);
+        }
+        if let Some(scheduler) = self.schedulers.get_mut(&name) {
+            scheduler.stats.record_completion(Duration::from_secs(entry.uptime()), exit_code);
         }
+        
+        let label = entry.label();
+        // 条目中保存的是展开了 {instance} 的配置
+        let log_path = entry.config.log_path();
+        let config = self.find_config(&name).cloned();
+        match config {
+            Some(config) if config.schedule.is_some() => {
+                info!(process = name.as_str(), pid = pid, %exit, timed_out = timed_out, "Scheduled process completed");
+                if timed_out || !exit.success() {
+                    let message = if timed_out {
+                        format!("scheduled run timed out after {}s", entry.uptime())
+                    } else {
+                        format!("scheduled run failed: {}", exit)
+                    };
+                    let alert = Alert::new(AlertEvent::JobFailed, &name, message).with_exit_code(exit_code);
+                    self.alerter.notify(alert, log_path);
+                }
+            }
+            Some(config) if config.auto_restart => {
+                warn!(process = label.as_str(), pid = pid, %exit, "Process exited unexpectedly");
+                let alert = Alert::new(AlertEvent::Exit, &label, format!("exited unexpectedly: {}", exit))
+                    .with_exit_code(exit_code);
+                self.alerter.notify(alert, log_path);
+                self.crashed_instances.entry(name.clone()).or_default().push(entry.instance);
+                self.schedule_restart(&config, entry.uptime());
+                self.run_pending_restarts().await;
+            }
+            _ if exit.success() => {
+                info!(process = label.as_str(), pid = pid, %exit, "Process exited");
+            }
+            _ => {
+                warn!(process = label.as_str(), pid = pid, %exit, "Process exited");
+                let alert = Alert::new(AlertEvent::Exit, &label, format!("exited unexpectedly: {}", exit))
+                    .with_exit_code(exit_code);
+                self.alerter.notify(alert, log_path);
+            }
+        }
+        
+        self.process_manager.save_state(&self.state_file)?;
         Ok(())
     }
     
     async fn start_processes(&mut self) -> Result<(), DaemonError> {
-        for process_config in &self.config.processes {
+        let names: Vec<String> = self.config.processes.iter().map(|p| p.name.clone()).collect();
+        self.queue_starts(&names, "startup");
+        self.run_pending_starts().await;
+        Ok(())
+    }
+    
+    /// Queue processes to start in dependency order once their dependencies are up
+    fn queue_starts(&mut self, names: &[String], reason: &'static str) {
+        // 配置已经过 validate_config 校验，不存在循环依赖
+        let order = config::start_order(&self.config.processes)
+            .unwrap_or_else(|_| self.config.processes.iter().map(|p| p.name.clone()).collect());
+        for name in order.iter().filter(|n| names.contains(n)) {
+            let config = match self.find_config(name) {
+                Some(config) => config,
+                None => continue,
+            };
             // Cron 进程不在启动时启动，等待 cron 时间点
-            if process_config.schedule.is_some() {
-                info!(process = process_config.name.as_str(), "Cron process - waiting for schedule");
+            if config.schedule.is_some() {
+                info!(process = name.as_str(), "Cron process - waiting for schedule");
                 continue;
             }
-            
-            if let Err(e) = self.process_manager.spawn(process_config).await {
-                error!(
-                    process = process_config.name.as_str(),
-                    error = %e,
-                    "Failed to start process"
-                );
+            if !config.depends_on.is_empty() {
+                info!(process = name.as_str(), depends_on = ?config.depends_on, "Waiting for dependencies");
+            }
+            self.pending_starts.push((name.clone(), reason));
+        }
+    }
+    
+    /// Start queued processes whose dependencies are running (and healthy, if checked)
+    async fn run_pending_starts(&mut self) {
+        let mut index = 0;
+        while index < self.pending_starts.len() {
+            let (name, reason) = self.pending_starts[index].clone();
+            let config = match self.find_config(&name) {
+                Some(config) if self.dependencies_ready(config) => config.clone(),
+                Some(_) => {
+                    index += 1;
+                    continue;
+                }
+                None => {
+                    self.pending_starts.remove(index);
+                    continue;
+                }
+            };
+            self.pending_starts.remove(index);
+            if let Err(e) = self.spawn_instances(&config, reason).await {
+                error!(process = name.as_str(), error = %e, "Failed to start process");
             }
         }
-        Ok(())
+    }
+    
+    fn dependencies_ready(&self, config: &ProcessConfig) -> bool {
+        config.depends_on.iter().all(|dep| {
+            let running = !self.process_manager.entries(dep).is_empty();
+            let checked = self.find_config(dep).is_some_and(|c| c.health_check.is_some());
+            let passed = || self.process_manager.entries(dep)
+                .iter()
+                .all(|e| self.health.get(&(dep.clone(), e.instance)).is_some_and(|h| h.passed));
+            running && (!checked || passed())
+        })
     }
     
     async fn monitor_and_restart(&mut self) -> Result<(), DaemonError> {
         info!("Running monitor check...");
-        let dead_names = self.process_manager.cleanup_dead();
-        info!("Dead processes found: {:?}", dead_names);
+        let dead = self.process_manager.cleanup_dead();
+        info!("Dead processes found: {:?}", dead.iter().map(|e| &e.name).collect::<Vec<_>>());
         
-        for name in dead_names {
+        for entry in dead {
+            let name = entry.name.clone();
+            self.record_end(&name, entry.pid, entry.uptime(), None, "dead");
+            self.health.remove(&(name.clone(), entry.instance));
+            
             if let Some(config) = self.find_config(&name) {
                 // Cron 进程不自动重启
                 if config.schedule.is_some() {
                     continue;
                 }
                 if config.auto_restart {
-                    warn!(process = name.as_str(), "Auto-restarting dead process");
+                    warn!(process = entry.label().as_str(), "Dead process detected");
                     let config = config.clone();
-                    if let Err(e) = self.process_manager.spawn(&config).await {
-                        error!(
-                            process = name.as_str(),
-                            error = %e,
-                            "Failed to restart process"
-                        );
+                    self.crashed_instances.entry(name.clone()).or_default().push(entry.instance);
+                    self.schedule_restart(&config, entry.uptime());
+                }
+            }
+        }
+        self.run_pending_restarts().await;
+        Ok(())
+    }
+    
+    /// Start health probes that are due; results come back as `ProcessEvent::HealthChecked`
+    fn run_health_checks(&mut self) {
+        let now = Instant::now();
+        let checks: Vec<(String, crate::config::HealthCheck)> = self.config.processes
+            .iter()
+            .filter(|p| p.schedule.is_none())
+            .filter_map(|p| p.health_check.clone().map(|c| (p.name.clone(), c)))
+            .collect();
+        
+        for (name, check) in checks {
+            // 每个实例单独探测，start_period 按各自的启动时间计算
+            let instances: Vec<(usize, u32, u64)> = self.process_manager.entries(&name)
+                .iter()
+                .map(|e| (e.instance, e.pid, e.uptime()))
+                .collect();
+            self.health.retain(|(n, i), _| n != &name || instances.iter().any(|(instance, _, _)| instance == i));
+            
+            for (instance, pid, uptime) in instances {
+                let state = self.health.entry((name.clone(), instance)).or_default();
+                if !state.is_due(&check, Duration::from_secs(uptime), now) {
+                    continue;
+                }
+                state.in_flight = true;
+                state.last_probe = Some(now);
+                
+                let name = name.clone();
+                let check = check.clone();
+                let events_tx = self.process_manager.event_sender();
+                tokio::spawn(async move {
+                    let result = health::run_probe(&check.probe, Duration::from_secs(check.timeout)).await;
+                    let _ = events_tx.send(ProcessEvent::HealthChecked { name, pid, result });
+                });
+            }
+        }
+    }
+    
+    /// Count a probe result and restart the instance once it is unhealthy
+    async fn handle_health_result(&mut self, name: String, pid: u32, result: Result<(), String>) -> Result<(), DaemonError> {
+        let check = match self.find_config(&name).and_then(|c| c.health_check.clone()) {
+            Some(check) => check,
+            None => {
+                self.clear_health(&name);
+                return Ok(());
+            }
+        };
+        
+        // 探测期间实例已被重启或停止，结果作废；重启后的实例使用新的状态
+        let (instance, label) = match self.process_manager.entries(&name).iter().find(|e| e.pid == pid) {
+            Some(entry) => (entry.instance, entry.label()),
+            None => return Ok(()),
+        };
+        
+        let state = self.health.entry((name.clone(), instance)).or_default();
+        let was_failing = state.failures > 0;
+        let reason = result.as_ref().err().cloned();
+        let threshold_reached = state.on_result(&check, result);
+        
+        match reason {
+            None if was_failing => info!(process = label.as_str(), "Health check recovered"),
+            None => debug!(process = label.as_str(), "Health check passed"),
+            Some(reason) if !threshold_reached => {
+                warn!(
+                    process = label.as_str(),
+                    failures = state.failures,
+                    retries = check.retries,
+                    reason = reason.as_str(),
+                    "Health check failed"
+                );
+            }
+            Some(reason) => {
+                error!(
+                    process = label.as_str(),
+                    failures = state.failures,
+                    reason = reason.as_str(),
+                    "Process is unhealthy, restarting"
+                );
+                let message = format!("health check failed {} times, restarting: {}", state.failures, reason);
+                self.alerter.notify(Alert::new(AlertEvent::HealthCheck, &label, message), None);
+                // 只重启未通过检查的实例，其余实例保持运行
+                if let Some(config) = self.find_config(&name).cloned() {
+                    if let Err(e) = self.restart_instance_and_record(&config, instance, "health_check").await {
+                        error!(process = label.as_str(), error = %e, "Failed to restart unhealthy process");
                     }
                 }
+                self.process_manager.save_state(&self.state_file)?;
             }
         }
+        // 依赖本进程的进程可能在等待首次检查通过
+        self.run_pending_starts().await;
         Ok(())
     }
     
+    /// Sample CPU time of every process and restart those that cross a watchdog threshold
+    async fn run_watchdog(&mut self) {
+        let now = Instant::now();
+        let mut violations = Vec::new();
+        let mut live_pids = Vec::new();
+        
+        for config in &self.config.processes {
+            // 调度任务由 cron 控制，不做看门狗重启，只采样 CPU 供 status 显示
+            let watchdog = Watchdog::from_config(config).filter(|_| config.schedule.is_none());
+            let window = watchdog.as_ref().map_or(Duration::ZERO, |w| w.cpu_window);
+            for entry in self.process_manager.entries(&config.name) {
+                live_pids.push(entry.pid);
+                let sampler = self.cpu_samplers.entry(entry.pid).or_default();
+                if let Some(cpu_time) = procfs::read_cpu_time(entry.pid) {
+                    sampler.push(now, cpu_time, window);
+                }
+                let watchdog = match watchdog {
+                    Some(ref w) => w,
+                    None => continue,
+                };
+                let memory = self.process_manager.get_process_memory(entry.pid);
+                if let Some(violation) = watchdog.check(sampler, memory, Duration::from_secs(entry.uptime())) {
+                    violations.push((config.clone(), entry.instance, entry.pid, violation));
+                }
+            }
+        }
+        self.cpu_samplers.retain(|pid, _| live_pids.contains(pid));
+        
+        // 只重启越限的实例
+        for (config, instance, pid, violation) in violations {
+            let label = self.process_manager.entries(&config.name)
+                .iter()
+                .find(|e| e.pid == pid)
+                .map_or_else(|| config.name.clone(), |e| e.label());
+            warn!(process = label.as_str(), pid = pid, %violation, "Watchdog restarting process");
+            let alert = Alert::new(AlertEvent::Restart, &label, format!("watchdog restart: {}", violation));
+            self.alerter.notify(alert, None);
+            if let Err(e) = self.restart_instance_and_record(&config, instance, violation.reason()).await {
+                error!(process = config.name.as_str(), error = %e, "Failed to restart process");
+            }
+        }
+    }
+    
+    /// Fill in what only the running daemon knows: health, CPU usage and restart count
+    fn apply_runtime(&self, statuses: &mut [ProcessStatus]) {
+        for status in statuses.iter_mut() {
+            status.cpu = self.cpu_samplers.get(&status.pid).and_then(|s| s.latest());
+            status.restarts = self.restart_counts.get(&status.name).copied().unwrap_or(0);
+            status.next_run = self.next_run(&status.name);
+
+            let check = self.find_config(&status.name).and_then(|c| c.health_check.as_ref());
+            let key = (status.name.clone(), status.instance.unwrap_or(0));
+            let unhealthy = check.is_some_and(|check| self.health.get(&key).is_some_and(|h| h.is_unhealthy(check)));
+            if unhealthy && status.state == ProcessState::Running {
+                status.state = ProcessState::Unhealthy;
+            }
+        }
+    }
+    
+    /// Decide whether and when an auto-restart process that died is restarted
+    fn schedule_restart(&mut self, config: &ProcessConfig, uptime: u64) {
+        let policy = RestartPolicy::from_config(config);
+        let tracker = self.restart_trackers.entry(config.name.clone()).or_default();
+        
+        match tracker.on_exit(&policy, Duration::from_secs(uptime), Instant::now()) {
+            RestartDecision::Restart(delay) => {
+                warn!(
+                    process = config.name.as_str(),
+                    delay_secs = delay.as_secs_f64(),
+                    failures = tracker.consecutive_failures(),
+                    "Scheduling restart"
+                );
+            }
+            RestartDecision::Fatal => {
+                error!(
+                    process = config.name.as_str(),
+                    max_restarts = policy.max_restarts,
+                    restart_window = config.restart_window,
+                    "Process is crash-looping, not restarting until started manually"
+                );
+                let message = format!(
+                    "crash loop: more than {} restarts within {}s, not restarting until started manually",
+                    policy.max_restarts, config.restart_window
+                );
+                self.alerter.notify(Alert::new(AlertEvent::CrashLoop, &config.name, message), None);
+            }
+        }
+    }
+    
+    /// Restart processes whose backoff delay has elapsed
+    async fn run_pending_restarts(&mut self) {
+        let now = Instant::now();
+        let due: Vec<String> = self.restart_trackers
+            .iter()
+            .filter(|(_, t)| t.is_due(now))
+            .map(|(name, _)| name.clone())
+            .collect();
+        
+        for name in due {
+            if let Some(tracker) = self.restart_trackers.get_mut(&name) {
+                tracker.next_restart = None;
+            }
+            let config = match self.find_config(&name) {
+                Some(c) => c.clone(),
+                None => continue,
+            };
+            
+            // 只补齐退出的实例，其余实例保持运行
+            let mut instances = self.crashed_instances.remove(&name).unwrap_or_default();
+            if instances.is_empty() {
+                instances.push(0);
+            }
+            instances.sort_unstable();
+            instances.dedup();
+            instances.retain(|i| *i < config.instances);
+            
+            for instance in instances {
+                if self.process_manager.entries(&name).iter().any(|e| e.instance == instance) {
+                    continue;
+                }
+                let label = if config.instances > 1 { format!("{}:{}", name, instance) } else { name.clone() };
+                info!(process = label.as_str(), "Auto-restarting process");
+                match self.spawn_process(&config, Some(instance), "auto_restart").await {
+                    Ok(pid) => {
+                        self.count_restart(&name);
+                        let alert = Alert::new(AlertEvent::Restart, &label, format!("auto-restarted with PID {}", pid));
+                        self.alerter.notify(alert, None);
+                    }
+                    Err(e) => {
+                        error!(process = label.as_str(), error = %e, "Failed to restart process");
+                        // 启动失败同样计入退避
+                        self.crashed_instances.entry(name.clone()).or_default().push(instance);
+                        self.schedule_restart(&config, 0);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Status row for a process that has no running instance
+    ///
+    /// Covers processes waiting to be restarted or that gave up, and scheduled
+    /// processes waiting for their next run.
+    fn idle_status(&self, name: &str) -> Option<ProcessStatus> {
+        let state = match self.restart_trackers.get(name) {
+            _ if self.pending_starts.iter().any(|(n, _)| n == name) => ProcessState::Waiting,
+            Some(tracker) if tracker.fatal => ProcessState::Fatal,
+            Some(tracker) if tracker.next_restart.is_some() => ProcessState::Backoff,
+            _ if self.schedulers.contains_key(name) => ProcessState::Stopped,
+            _ => return None,
+        };
+        Some(ProcessStatus {
+            name: name.to_string(),
+            pid: 0,
+            state,
+            uptime: 0,
+            memory: None,
+            group_memory: None,
+            cpu: None,
+            threads: None,
+            fds: None,
+            restarts: self.restart_counts.get(name).copied().unwrap_or(0),
+            next_run: self.next_run(name),
+            cgroup: None,
+            instance: None,
+        })
+    }
+    
+    /// Wall-clock time of the next scheduled run
+    fn next_run(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.schedulers.get(name)?.next_run_time()
+    }
+    
     async fn shutdown(&mut self) -> Result<(), DaemonError> {
         info!("Shutting down daemon...");
         
-        let names = self.process_manager.process_names();
+        // 按启动顺序的逆序停止，依赖方先于被依赖方退出；不在配置中的进程最先停止
+        let running = self.process_manager.process_names();
+        let order = config::start_order(&self.config.processes).unwrap_or_default();
+        let mut names: Vec<String> = running.iter().filter(|n| !order.contains(n)).cloned().collect();
+        names.extend(order.into_iter().rev().filter(|n| running.contains(n)));
         for name in names {
             info!(process = name.as_str(), "Stopping process");
-            if let Err(e) = self.process_manager.stop(&name).await {
+            if let Err(e) = self.stop_and_record(&name, "shutdown").await {
                 error!(
                     process = name.as_str(),
                     error = %e,
@@ -277,30 +860,188 @@ impl Daemon {
         Ok(())
     }
     
-    fn reload_config(&mut self) -> Result<(), DaemonError> {
+    /// Reload the config file and reconcile running processes with it
+    async fn reload_config(&mut self) -> Result<(), DaemonError> {
         info!("Reloading configuration...");
         
-        let new_config = load_config(&self.config_path)?;
+        let new_config = match load_config(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!(error = %e, "Failed to reload configuration, keeping current configuration");
+                return Err(e);
+            }
+        };
         
-        for new_proc in &new_config.processes {
-            if !self.config.processes.iter().any(|p| p.name == new_proc.name) {
-                info!(process = new_proc.name.as_str(), "Adding new process");
+        let diff = config::diff_processes(&self.config.processes, &new_config.processes);
+        if diff.is_empty() {
+            info!("No processes added, removed or changed");
+        }
+        let old_config = std::mem::replace(&mut self.config, new_config);
+        self.alerter = Alerter::new(&self.config.alerts);
+        
+        // 未变化的调度保留原有的下次执行时间
+        let mut schedulers = build_schedulers(&self.config);
+        for (name, scheduler) in schedulers.iter_mut() {
+            let old_schedule = old_config.processes.iter()
+                .find(|p| &p.name == name)
+                .and_then(|p| p.schedule.as_ref());
+            if old_schedule.is_some() && old_schedule == self.find_config(name).and_then(|p| p.schedule.as_ref()) {
+                if let Some(old) = self.schedulers.remove(name) {
+                    *scheduler = old;
+                }
             }
         }
+        self.schedulers = schedulers;
         
-        for old_proc in &self.config.processes {
-            if !new_config.processes.iter().any(|p| p.name == old_proc.name) {
-                info!(process = old_proc.name.as_str(), "Removing process");
+        for name in &diff.removed {
+            info!(process = name.as_str(), "Removing process");
+            self.pending_starts.retain(|(n, _)| n != name);
+            self.restart_trackers.remove(name);
+            self.crashed_instances.remove(name);
+            self.clear_health(name);
+            if !self.process_manager.entries(name).is_empty() {
+                if let Err(e) = self.stop_and_record(name, "removed").await {
+                    error!(process = name.as_str(), error = %e, "Failed to stop removed process");
+                }
             }
         }
         
-        self.config = new_config;
-        info!("Configuration reloaded");
+        for name in &diff.added {
+            info!(process = name.as_str(), "Adding new process");
+        }
+        self.queue_starts(&diff.added, "reload");
+        self.run_pending_starts().await;
+        
+        for name in &diff.changed {
+            let config = match self.find_config(name) {
+                Some(config) => config.clone(),
+                None => continue,
+            };
+            // 正在执行的调度任务跑完即可，下次执行使用新配置
+            if config.schedule.is_some() || self.process_manager.entries(name).is_empty() {
+                continue;
+            }
+            info!(process = name.as_str(), "Process configuration changed, restarting");
+            self.restart_trackers.remove(name);
+            if let Err(e) = self.restart_and_record(&config, "reload").await {
+                error!(process = name.as_str(), error = %e, "Failed to restart changed process");
+            }
+        }
+        
+        // 停止/重载信号等无需重启的设置立即作用于运行中的进程
+        for config in &self.config.processes {
+            if !diff.changed.contains(&config.name) {
+                self.process_manager.update_config(config);
+            }
+        }
+        self.scale_instances().await;
+        
+        self.process_manager.save_state(&self.state_file)?;
+        info!(
+            added = diff.added.len(),
+            removed = diff.removed.len(),
+            changed = diff.changed.len(),
+            "Configuration reloaded"
+        );
         Ok(())
     }
     
     fn find_config(&self, name: &str) -> Option<&ProcessConfig> {
-        self.config.processes.iter().find(|p| &p.name == name)
+        self.config.processes.iter().find(|p| p.name == name)
+    }
+    
+    /// Resolve a CLI target ("web" or "web:1") to its config and instance index
+    fn find_target(&self, target: &str) -> Result<(ProcessConfig, Option<usize>), DaemonError> {
+        let (name, instance) = match self.find_config(target) {
+            // 进程名本身可能带冒号
+            Some(_) => (target, None),
+            None => config::split_instance(target),
+        };
+        let config = self.find_config(name)
+            .ok_or_else(|| DaemonError::Config(format!("Process '{}' not found in config", target)))?;
+        if let Some(instance) = instance {
+            if instance >= config.instances {
+                return Err(DaemonError::Config(format!(
+                    "Process '{}' has {} instance(s), no instance {}", name, config.instances, instance
+                )));
+            }
+        }
+        Ok((config.clone(), instance))
+    }
+    
+    /// Handle a request received on the control socket
+    async fn handle_control(&mut self, request: PendingRequest) {
+        info!(command = ?request.command, "Control request");
+        
+        let result = match request.command.clone() {
+            ControlCommand::Start { name } => {
+                self.start_process(&name).await.map(|pid| ControlResponse::Started { pid })
+            }
+            ControlCommand::Stop { name } => {
+                self.stop_process(&name).await.map(|pids| ControlResponse::Stopped { pids })
+            }
+            ControlCommand::Restart { name } => {
+                self.restart_process(&name).await.map(|pids| ControlResponse::Restarted { pids })
+            }
+            ControlCommand::Status { name } => {
+                let statuses = match name {
+                    Some(name) => self.get_status(&name),
+                    None => Ok(self.get_all_status()),
+                };
+                statuses.map(|processes| ControlResponse::Status { processes })
+            }
+            ControlCommand::Reload => self.reload_config().await.map(|_| ControlResponse::Reloaded),
+            ControlCommand::ReloadProcess { name } => {
+                self.reload_process(&name).map(|pids| ControlResponse::ProcessReloaded { pids })
+            }
+            ControlCommand::Schedules { count } => Ok(ControlResponse::Schedules { schedules: self.schedules(count) }),
+            ControlCommand::RunJob { name } => {
+                self.run_job(&name).await.map(|pid| ControlResponse::Started { pid })
+            }
+            ControlCommand::Shutdown => {
+                self.trigger_shutdown();
+                Ok(ControlResponse::ShuttingDown)
+            }
+            ControlCommand::Metrics => Ok(ControlResponse::Metrics { text: self.render_metrics() }),
+            ControlCommand::History { name, limit } => match self.storage {
+                Some(ref storage) => storage.get_history(&name, limit)
+                    .map(|records| ControlResponse::History { records })
+                    .map_err(DaemonError::from),
+                None => Err(DaemonError::Control("History database is not available".to_string())),
+            },
+            ControlCommand::LogFile { name } => match self.find_target(&name) {
+                Ok((config, instance)) => config.for_instance(instance.unwrap_or(0)).log_path()
+                    .map(|path| ControlResponse::LogFile { path })
+                    .ok_or_else(|| DaemonError::Config(format!("Process '{}' does not capture output", name))),
+                Err(e) => Err(e),
+            },
+        };
+        
+        let response = result.unwrap_or_else(|e| {
+            warn!(error = %e, "Control request failed");
+            ControlResponse::from_error(&e)
+        });
+        request.respond(response);
+    }
+    
+    /// Prometheus metrics for every configured process and scheduled job
+    fn render_metrics(&self) -> String {
+        let statuses = self.get_all_status();
+        let processes: Vec<ProcessMetrics> = self.config.processes
+            .iter()
+            .map(|p| {
+                let mut metrics = ProcessMetrics::collect(&p.name, &statuses);
+                metrics.restarts = self.restart_counts.get(&p.name).copied().unwrap_or(0) as u64;
+                metrics.last_exit_code = self.last_exit_codes.get(&p.name).copied();
+                metrics
+            })
+            .collect();
+        let mut jobs: Vec<_> = self.schedulers
+            .iter()
+            .map(|(name, scheduler)| (name.clone(), scheduler.stats.clone()))
+            .collect();
+        jobs.sort_by(|a, b| a.0.cmp(&b.0));
+        metrics::render(&processes, &jobs)
     }
     
     pub fn trigger_shutdown(&self) {
@@ -309,39 +1050,359 @@ impl Daemon {
         }
     }
     
-    pub async fn start_process(&mut self, name: &str) -> Result<u32, DaemonError> {
-        if let Some(config) = self.find_config(name) {
-            let config = config.clone();
-            let pid = self.process_manager.spawn(&config).await?;
-            self.process_manager.save_state(&self.state_file)?;
-            Ok(pid)
-        } else {
-            Err(DaemonError::Config(format!("Process '{}' not found in config", name)))
+    /// Run a storage operation, logging instead of failing when it errors
+    fn record<F>(&self, op: F)
+    where
+        F: FnOnce(&Storage) -> rusqlite::Result<()>,
+    {
+        if let Some(ref storage) = self.storage {
+            if let Err(e) = op(storage) {
+                warn!(error = %e, "Failed to record process history");
+            }
+        }
+    }
+    
+    /// Record the end of a process, and of its scheduled run if it is a job
+    fn record_end(&mut self, name: &str, pid: u32, uptime: u64, exit_code: Option<i32>, reason: &str) {
+        let timed_out = self.timed_out.remove(&pid);
+        self.record(|s| {
+            s.record_end(name, pid, exit_code, reason)?;
+            s.record_job_end(name, pid, exit_code, timed_out)?;
+            s.update_uptime(name, uptime as i64)
+        });
+    }
+    
+    /// Count a restart for `m7d status` and record it in storage
+    fn count_restart(&mut self, name: &str) {
+        *self.restart_counts.entry(name.to_string()).or_default() += 1;
+        self.record(|s| s.record_restart(name));
+    }
+    
+    /// Spawn a process and record the start with its reason
+    ///
+    /// Without an explicit instance the lowest free index is used.
+    async fn spawn_process(&mut self, config: &ProcessConfig, instance: Option<usize>, reason: &str) -> Result<u32, DaemonError> {
+        let pid = match instance {
+            Some(instance) => self.process_manager.spawn_instance(config, instance).await?,
+            None => self.process_manager.spawn(config).await?,
+        };
+        self.record(|s| s.record_start(&config.name, pid, config.auto_restart, reason).map(|_| ()));
+        Ok(pid)
+    }
+    
+    /// Spawn every configured instance that is not running
+    async fn spawn_instances(&mut self, config: &ProcessConfig, reason: &str) -> Result<Vec<u32>, DaemonError> {
+        let mut pids = Vec::new();
+        for instance in 0..config.instances {
+            if !self.process_manager.entries(&config.name).iter().any(|e| e.instance == instance) {
+                pids.push(self.spawn_process(config, Some(instance), reason).await?);
+            }
+        }
+        Ok(pids)
+    }
+    
+    /// Bring running processes to their configured instance count after a reload
+    async fn scale_instances(&mut self) {
+        let configs: Vec<ProcessConfig> = self.config.processes
+            .iter()
+            .filter(|p| p.schedule.is_none() && !self.process_manager.entries(&p.name).is_empty())
+            .cloned()
+            .collect();
+        for config in configs {
+            let extra: Vec<usize> = self.process_manager.entries(&config.name)
+                .iter()
+                .map(|e| e.instance)
+                .filter(|i| *i >= config.instances)
+                .collect();
+            for instance in extra {
+                info!(process = config.name.as_str(), instance = instance, "Stopping surplus instance");
+                if let Err(e) = self.stop_instance_and_record(&config.name, instance, "reload").await {
+                    error!(process = config.name.as_str(), error = %e, "Failed to stop surplus instance");
+                }
+            }
+            if let Err(e) = self.spawn_instances(&config, "reload").await {
+                error!(process = config.name.as_str(), error = %e, "Failed to start instances");
+            }
+        }
+    }
+    
+    /// Stop all instances of a process and record why they ended
+    async fn stop_and_record(&mut self, name: &str, reason: &str) -> Result<Vec<u32>, DaemonError> {
+        let uptimes: Vec<(u32, u64)> = self.process_manager.entries(name)
+            .iter()
+            .map(|e| (e.pid, e.uptime()))
+            .collect();
+        let stopped = self.process_manager.stop(name).await?;
+        self.clear_health(name);
+        for (pid, uptime) in uptimes {
+            if stopped.contains(&pid) {
+                self.record_end(name, pid, uptime, None, reason);
+            }
+        }
+        Ok(stopped)
+    }
+    
+    /// Stop a single instance and record why it ended
+    async fn stop_instance_and_record(&mut self, name: &str, instance: usize, reason: &str) -> Result<u32, DaemonError> {
+        let uptime = self.process_manager.entries(name)
+            .iter()
+            .find(|e| e.instance == instance)
+            .map_or(0, |e| e.uptime());
+        let pid = self.process_manager.stop_instance(name, instance).await?;
+        self.health.remove(&(name.to_string(), instance));
+        if let Some(crashed) = self.crashed_instances.get_mut(name) {
+            crashed.retain(|i| *i != instance);
+        }
+        self.record_end(name, pid, uptime, None, reason);
+        Ok(pid)
+    }
+    
+    /// Restart a single instance and record it
+    async fn restart_instance_and_record(&mut self, config: &ProcessConfig, instance: usize, reason: &str) -> Result<u32, DaemonError> {
+        let name = config.name.as_str();
+        let old = self.process_manager.entries(name)
+            .iter()
+            .find(|e| e.instance == instance)
+            .map(|e| (e.pid, e.uptime()));
+        let pid = self.process_manager.restart_instance(config, instance).await?;
+        self.health.remove(&(name.to_string(), instance));
+        if let Some((old_pid, uptime)) = old {
+            self.record_end(name, old_pid, uptime, None, reason);
         }
+        self.record(|s| s.record_start(name, pid, config.auto_restart, reason).map(|_| ()));
+        self.count_restart(name);
+        Ok(pid)
     }
     
-    pub async fn stop_process(&mut self, name: &str) -> Result<Vec<u32>, DaemonError> {
-        let pids = self.process_manager.stop(name).await?;
+    /// Drop the health state of every instance of a process
+    fn clear_health(&mut self, name: &str) {
+        self.health.retain(|(n, _), _| n != name);
+    }
+    
+    /// Restart a process through `ProcessManager::restart` and record it
+    async fn restart_and_record(&mut self, config: &ProcessConfig, reason: &str) -> Result<Vec<u32>, DaemonError> {
+        let name = config.name.as_str();
+        let uptimes: Vec<(u32, u64)> = self.process_manager.entries(name)
+            .iter()
+            .map(|e| (e.pid, e.uptime()))
+            .collect();
+        let pids = self.process_manager.restart(config).await?;
+        self.clear_health(name);
+        self.crashed_instances.remove(name);
+        for (pid, uptime) in uptimes {
+            self.record_end(name, pid, uptime, None, reason);
+        }
+        for &pid in &pids {
+            self.record(|s| s.record_start(name, pid, config.auto_restart, reason).map(|_| ()));
+        }
+        self.count_restart(name);
+        Ok(pids)
+    }
+    
+    pub async fn start_process(&mut self, target: &str) -> Result<u32, DaemonError> {
+        let (config, instance) = self.find_target(target)?;
+        let name = config.name.as_str();
+        let pid = match instance {
+            Some(instance) => {
+                if let Some(entry) = self.process_manager.entries(name).iter().find(|e| e.instance == instance) {
+                    return Err(DaemonError::AlreadyRunning { name: target.to_string(), pid: entry.pid });
+                }
+                if let Some(crashed) = self.crashed_instances.get_mut(name) {
+                    crashed.retain(|i| *i != instance);
+                }
+                self.spawn_process(&config, Some(instance), "manual").await?
+            }
+            None => {
+                // 手动启动视为运维介入，清除退避/fatal 状态，也不再等待依赖
+                self.restart_trackers.remove(name);
+                self.crashed_instances.remove(name);
+                self.pending_starts.retain(|(n, _)| n != name);
+                // 补齐缺少的实例；实例已全部运行时与以前一样再启动一个（受 max_instances 限制）
+                match self.spawn_instances(&config, "manual").await?.first() {
+                    Some(&pid) => pid,
+                    None => self.spawn_process(&config, None, "manual").await?,
+                }
+            }
+        };
+        self.process_manager.save_state(&self.state_file)?;
+        Ok(pid)
+    }
+    
+    pub async fn stop_process(&mut self, target: &str) -> Result<Vec<u32>, DaemonError> {
+        let name = match self.find_target(target) {
+            Ok((config, Some(instance))) => {
+                let pid = self.stop_instance_and_record(&config.name, instance, "stopped").await?;
+                self.process_manager.save_state(&self.state_file)?;
+                return Ok(vec![pid]);
+            }
+            // 实例序号越界；不在配置中的进程（已从配置删除但仍在运行）照常停止
+            Err(e) if self.find_config(config::split_instance(target).0).is_some() => return Err(e),
+            _ => target,
+        };
+        // 取消等待中的重启或启动；只处于退避/fatal/等待依赖状态的进程视为已停止
+        let was_pending = self.pending_starts.iter().any(|(n, _)| n == name);
+        self.pending_starts.retain(|(n, _)| n != name);
+        let had_tracker = self.restart_trackers.remove(name).is_some() || was_pending;
+        self.crashed_instances.remove(name);
+        let pids = match self.stop_and_record(name, "stopped").await {
+            Err(DaemonError::NotRunning { .. }) if had_tracker => Vec::new(),
+            result => result?,
+        };
         self.process_manager.save_state(&self.state_file)?;
         Ok(pids)
     }
     
-    pub async fn restart_process(&mut self, name: &str) -> Result<Vec<u32>, DaemonError> {
-        if let Some(config) = self.find_config(name) {
-            let config = config.clone();
-            let pids = self.process_manager.restart(&config).await?;
-            self.process_manager.save_state(&self.state_file)?;
+    pub async fn restart_process(&mut self, target: &str) -> Result<Vec<u32>, DaemonError> {
+        let (config, instance) = self.find_target(target)?;
+        let pids = match instance {
+            Some(instance) => vec![self.restart_instance_and_record(&config, instance, "restart").await?],
+            None => {
+                self.restart_trackers.remove(&config.name);
+                self.restart_and_record(&config, "restart").await?
+            }
+        };
+        self.process_manager.save_state(&self.state_file)?;
+        Ok(pids)
+    }
+    
+    /// Send the configured `reload_signal` to a process without respawning it
+    pub fn reload_process(&self, target: &str) -> Result<Vec<u32>, DaemonError> {
+        let (config, instance) = self.find_target(target)?;
+        let signal = config.reload_signal.as_deref()
+            .ok_or_else(|| DaemonError::Config(format!("Process '{}' has no reload_signal configured", config.name)))?;
+        #[cfg(unix)]
+        {
+            let pids = self.process_manager.signal(&config.name, instance, crate::signal::parse(signal)?)?;
+            info!(process = target, signal = signal, pids = ?pids, "Sent reload signal");
             Ok(pids)
-        } else {
-            Err(DaemonError::Config(format!("Process '{}' not found in config", name)))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (signal, instance);
+            Err(DaemonError::Signal("Not supported".to_string()))
         }
     }
     
-    pub fn get_status(&self, name: &str) -> Result<Vec<ProcessStatus>, DaemonError> {
-        self.process_manager.status(name)
+    pub fn get_status(&self, target: &str) -> Result<Vec<ProcessStatus>, DaemonError> {
+        if let Ok((config, Some(instance))) = self.find_target(target) {
+            let statuses: Vec<ProcessStatus> = self.get_status(&config.name)?
+                .into_iter()
+                .filter(|s| s.instance == Some(instance) || (s.instance.is_none() && instance == 0))
+                .collect();
+            if statuses.is_empty() {
+                return Err(DaemonError::NotRunning { name: target.to_string() });
+            }
+            return Ok(statuses);
+        }
+        let name = target;
+        match self.process_manager.status(name) {
+            Err(e) => self.idle_status(name).map(|s| vec![s]).ok_or(e),
+            Ok(mut statuses) => {
+                self.apply_runtime(&mut statuses);
+                Ok(statuses)
+            }
+        }
     }
     
     pub fn get_all_status(&self) -> Vec<ProcessStatus> {
-        self.process_manager.status_all()
+        let mut statuses = self.process_manager.status_all();
+        self.apply_runtime(&mut statuses);
+        for config in &self.config.processes {
+            if self.process_manager.entries(&config.name).is_empty() {
+                statuses.extend(self.idle_status(&config.name));
+            }
+        }
+        statuses
+    }
+}
+
+/// Build per-process schedulers from the config
+fn build_schedulers(config: &DaemonConfig) -> HashMap<String, Scheduler> {
+    let global_interval = config.daemon.as_ref()
+        .map(|d| d.check_interval)
+        .unwrap_or(5);
+    
+    config.processes
+        .iter()
+        .filter_map(|p| {
+            p.schedule.as_ref()
+                .map(|schedule| (p.name.clone(), Scheduler::from_config(&p.name, schedule, global_interval)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_health_check_restarts_failing_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("daemon.toml");
+        std::fs::write(&config_path, r#"
+[[processes]]
+name = "web"
+command = "sleep"
+args = ["30"]
+instances = 2
+capture_output = false
+health_check = { type = "tcp", port = 1, retries = 2 }
+"#).unwrap();
+        let pid_file = dir.path().join("daemon.pid");
+        let mut daemon = Daemon::new_read_only(config_path, pid_file.to_str().unwrap()).unwrap();
+        let config = daemon.find_config("web").cloned().unwrap();
+        daemon.spawn_instances(&config, "startup").await.unwrap();
+
+        let pid = |daemon: &Daemon, instance: usize| {
+            daemon.process_manager.entries("web").iter().find(|e| e.instance == instance).unwrap().pid
+        };
+        let states = |daemon: &Daemon| {
+            let mut statuses = daemon.process_manager.status("web").unwrap();
+            daemon.apply_runtime(&mut statuses);
+            statuses.into_iter().map(|s| s.state).collect::<Vec<_>>()
+        };
+        let (healthy, failing) = (pid(&daemon, 0), pid(&daemon, 1));
+
+        daemon.handle_health_result("web".to_string(), healthy, Ok(())).await.unwrap();
+        daemon.handle_health_result("web".to_string(), failing, Err("down".to_string())).await.unwrap();
+        // 一次失败未用完 retries，仍显示为运行中
+        assert_eq!(states(&daemon), vec![ProcessState::Running, ProcessState::Running]);
+
+        // 达到阈值后只重启失败的实例
+        daemon.handle_health_result("web".to_string(), failing, Err("down".to_string())).await.unwrap();
+        assert_eq!(pid(&daemon, 0), healthy);
+        assert_ne!(pid(&daemon, 1), failing);
+        assert!(daemon.health.get(&("web".to_string(), 0)).is_some_and(|h| h.passed));
+        assert!(!daemon.health.contains_key(&("web".to_string(), 1)));
+
+        daemon.stop_and_record("web", "test").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_full_check_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("daemon.toml");
+        std::fs::write(&config_path, r#"
+[daemon]
+check_interval = 5
+
+[[processes]]
+name = "report"
+command = "true"
+schedule = { type = "cron", expression = "0 0 3 * * *" }
+"#).unwrap();
+        let new_daemon = || Daemon::new_read_only(config_path.clone(), dir.path().join("daemon.pid").to_str().unwrap()).unwrap();
+        let (mut daemon, other) = (new_daemon(), new_daemon());
+
+        // 间隔到达后完整检查一次并重新计时，之后每秒的调度检查不再触发
+        let overdue = Instant::now() - Duration::from_secs(10);
+        daemon.last_full_check = overdue;
+        daemon.monitor_scheduled().await.unwrap();
+        let checked = daemon.last_full_check;
+        assert!(checked > overdue);
+        daemon.monitor_scheduled().await.unwrap();
+        assert_eq!(daemon.last_full_check, checked);
+        assert!(other.last_full_check < checked);
     }
 }
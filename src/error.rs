@@ -1,7 +1,7 @@
-use std::fmt;
 use thiserror::Error;
 
 /// Main error type for the daemon process manager
+#[allow(dead_code)] // some variants are part of the exit-code table only
 #[derive(Debug, Error)]
 pub enum DaemonError {
     #[error("Configuration error: {0}")]
@@ -42,6 +42,12 @@ pub enum DaemonError {
 
     #[error("Process not found")]
     ProcessNotFound,
+
+    #[error("Control socket error: {0}")]
+    Control(String),
+
+    #[error("{message}")]
+    Remote { message: String, exit_code: i32 },
 }
 
 impl DaemonError {
@@ -61,6 +67,8 @@ impl DaemonError {
             DaemonError::Daemonize(_) => 75,
             DaemonError::Signal(_) => 70, // EX_SOFTWARE
             DaemonError::ProcessNotFound => 1,
+            DaemonError::Control(_) => 69, // EX_UNAVAILABLE
+            DaemonError::Remote { exit_code, .. } => *exit_code,
         }
     }
 }
@@ -72,6 +80,13 @@ impl From<daemonize::Error> for DaemonError {
     }
 }
 
+// 为 rusqlite::Error 实现 From trait
+impl From<rusqlite::Error> for DaemonError {
+    fn from(err: rusqlite::Error) -> Self {
+        DaemonError::Config(format!("Database error: {}", err))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,12 +139,14 @@ mod tests {
         assert_eq!(DaemonError::Daemonize("test".to_string()).exit_code(), 75);
         assert_eq!(DaemonError::Signal("test".to_string()).exit_code(), 70);
         assert_eq!(DaemonError::ProcessNotFound.exit_code(), 1);
-    }
-}
-
-// 为 rusqlite::Error 实现 From trait
-impl From<rusqlite::Error> for DaemonError {
-    fn from(err: rusqlite::Error) -> Self {
-        DaemonError::Config(format!("Database error: {}", err))
+        assert_eq!(DaemonError::Control("test".to_string()).exit_code(), 69);
+        assert_eq!(
+            DaemonError::Remote {
+                message: "test".to_string(),
+                exit_code: 78
+            }
+            .exit_code(),
+            78
+        );
     }
 }
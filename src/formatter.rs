@@ -1,6 +1,63 @@
 //! Output formatting utilities
 
-use crate::process::{ProcessState, ProcessStatus};
+use crate::cli::OutputFormat;
+use crate::config::{ProcessConfig, Schedule, ScheduleType};
+use crate::error::DaemonError;
+use crate::process::{ProcessState, ProcessStatus, ScheduleInfo};
+use crate::storage::{JobRun, ProcessStats};
+use serde::Serialize;
+
+/// 机器可读的状态记录：`ProcessStatus` 的全部字段加上配置元数据
+#[derive(Debug, Serialize)]
+pub struct StatusRecord<'a> {
+    #[serde(flatten)]
+    pub status: &'a ProcessStatus,
+    pub command: Option<&'a str>,
+    pub args: Option<&'a [String]>,
+    pub working_directory: Option<&'a str>,
+    pub log_file: Option<String>,
+    pub auto_restart: Option<bool>,
+    pub schedule: Option<&'a Schedule>,
+}
+
+impl<'a> StatusRecord<'a> {
+    pub fn new(status: &'a ProcessStatus, config: Option<&'a ProcessConfig>) -> Self {
+        Self {
+            status,
+            command: config.map(|c| c.command.as_str()),
+            args: config.map(|c| c.args.as_slice()),
+            working_directory: config.and_then(|c| c.working_directory.as_deref()),
+            log_file: config.and_then(|c| c.for_instance(status.instance.unwrap_or(0)).log_path()),
+            auto_restart: config.map(|c| c.auto_restart),
+            schedule: config.and_then(|c| c.schedule.as_ref()),
+        }
+    }
+}
+
+/// Print statuses in the requested output format
+pub fn print_status(statuses: &[ProcessStatus], configs: &[ProcessConfig], output: OutputFormat) -> Result<(), DaemonError> {
+    let records: Vec<StatusRecord> = statuses
+        .iter()
+        .map(|s| StatusRecord::new(s, configs.iter().find(|c| c.name == s.name)))
+        .collect();
+
+    match output {
+        OutputFormat::Table => print_status_table(statuses),
+        OutputFormat::Wide => print_status_table_wide(&records),
+        OutputFormat::Json | OutputFormat::Yaml => print_serialized(&records, output)?,
+    }
+    Ok(())
+}
+
+/// Print a value as YAML for `OutputFormat::Yaml`, JSON otherwise
+pub fn print_serialized<T: Serialize + ?Sized>(value: &T, output: OutputFormat) -> Result<(), DaemonError> {
+    let text = match output {
+        OutputFormat::Yaml => serde_yaml::to_string(value).map_err(|e| DaemonError::Io(std::io::Error::other(e)))?,
+        _ => serde_json::to_string_pretty(value).map_err(std::io::Error::from)? + "\n",
+    };
+    print!("{}", text);
+    Ok(())
+}
 
 /// Print status table (like pm2)
 pub fn print_status_table(statuses: &[ProcessStatus]) {
@@ -9,121 +66,257 @@ pub fn print_status_table(statuses: &[ProcessStatus]) {
         return;
     }
 
-    let name_col = "Name";
-    let pid_col = "PID";
-    let status_col = "Status";
-    let uptime_col = "Uptime";
-    let cpu_col = "CPU";
-    let memory_col = "Memory";
-
-    let min_name_w = 15;
-    let min_pid_w = 6;
-    let min_status_w = 8;
-    let min_uptime_w = 8;
-    let min_cpu_w = 5;
-    let min_memory_w = 10;
-
-    let name_w = std::cmp::max(
-        min_name_w,
-        statuses.iter().map(|s| s.name.len()).max().unwrap_or(0),
-    );
-    let pid_w = std::cmp::max(
-        min_pid_w,
-        statuses.iter().map(|s| s.pid.to_string().len()).max().unwrap_or(0),
-    );
-    let status_w = std::cmp::max(
-        min_status_w,
-        statuses
-            .iter()
-            .map(|s| format_state(&s.state).len())
-            .max()
-            .unwrap_or(0),
-    );
-    let uptime_w = std::cmp::max(
-        min_uptime_w,
-        statuses
-            .iter()
-            .map(|s| format_uptime(s.uptime).len())
-            .max()
-            .unwrap_or(0),
-    );
-    let cpu_w = min_cpu_w;
-    let memory_w = std::cmp::max(
-        min_memory_w,
-        statuses
+    let rows: Vec<Vec<String>> = statuses.iter().map(status_row).collect();
+    print_table(&STATUS_HEADERS, &rows);
+
+    print_cgroup_table(statuses);
+}
+
+/// Print the status table with next run, log file and command line columns
+fn print_status_table_wide(records: &[StatusRecord]) {
+    if records.is_empty() {
+        println!("No processes running");
+        return;
+    }
+
+    let headers: Vec<&str> = STATUS_HEADERS
+        .iter()
+        .copied()
+        .chain(["Next Run", "Log File", "Command"])
+        .collect();
+    let rows: Vec<Vec<String>> = records
+        .iter()
+        .map(|r| {
+            let mut row = status_row(r.status);
+            row.push(
+                r.status
+                    .next_run
+                    .map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S").to_string()),
+            );
+            row.push(r.log_file.clone().unwrap_or_else(|| "-".to_string()));
+            row.push(match (r.command, r.args) {
+                (Some(command), Some(args)) if !args.is_empty() => format!("{} {}", command, args.join(" ")),
+                (Some(command), _) => command.to_string(),
+                _ => "-".to_string(),
+            });
+            row
+        })
+        .collect();
+
+    print_table(&headers, &rows);
+
+    let statuses: Vec<ProcessStatus> = records.iter().map(|r| r.status.clone()).collect();
+    print_cgroup_table(&statuses);
+}
+
+const STATUS_HEADERS: [&str; 9] = ["Name", "PID", "Status", "Uptime", "↺", "CPU", "Memory", "Threads", "FDs"];
+
+fn status_row(s: &ProcessStatus) -> Vec<String> {
+    vec![
+        s.label(),
+        format_pid(s.pid),
+        format_state(&s.state),
+        format_uptime(s.uptime),
+        s.restarts.to_string(),
+        format_cpu(s.cpu),
+        // 优先显示整个进程组的内存，多进程服务（Swoole master + worker）更准确
+        format_memory(s.group_memory.or(s.memory)),
+        format_count(s.threads),
+        format_count(s.fds),
+    ]
+}
+
+/// Print cgroup usage for processes running with cgroup limits
+fn print_cgroup_table(statuses: &[ProcessStatus]) {
+    // 同一进程的多个实例共享一个 cgroup，只显示一次
+    let mut seen = std::collections::HashSet::new();
+    let rows: Vec<Vec<String>> = statuses
+        .iter()
+        .filter_map(|s| s.cgroup.as_ref().map(|usage| (s, usage)))
+        .filter(|(s, _)| seen.insert(s.name.as_str()))
+        .map(|(s, usage)| {
+            vec![
+                s.name.clone(),
+                format!(
+                    "{} / {}",
+                    format_memory(usage.memory_current),
+                    usage.memory_max.map_or("max".to_string(), |m| format_memory(Some(m)))
+                ),
+                format!(
+                    "{} / {}",
+                    usage.pids_current.map_or("N/A".to_string(), |p| p.to_string()),
+                    usage.pids_max.map_or("max".to_string(), |p| p.to_string())
+                ),
+                usage
+                    .cpu_usage_usec
+                    .map_or("N/A".to_string(), |us| format!("{:.1}s", us as f64 / 1_000_000.0)),
+            ]
+        })
+        .collect();
+
+    if !rows.is_empty() {
+        println!();
+        print_table(&["Cgroup", "Memory", "Pids", "CPU Time"], &rows);
+    }
+}
+
+/// Print process statistics table
+pub fn print_stats_table(stats: &[ProcessStats]) {
+    if stats.is_empty() {
+        println!("No statistics recorded");
+        return;
+    }
+
+    let headers = ["Name", "Starts", "Restarts", "Failures", "Avg Uptime", "Last Start", "Last Exit"];
+    let rows: Vec<Vec<String>> = stats
+        .iter()
+        .map(|s| {
+            vec![
+                s.name.clone(),
+                s.total_starts.to_string(),
+                s.total_restarts.to_string(),
+                s.total_failures.to_string(),
+                format_uptime(s.avg_uptime_seconds as u64),
+                s.last_start_time
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "N/A".to_string()),
+                s.last_exit_code
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "N/A".to_string()),
+            ]
+        })
+        .collect();
+
+    print_table(&headers, &rows);
+}
+
+/// Print recent runs of a scheduled process
+pub fn print_jobs_table(runs: &[JobRun]) {
+    let headers = ["Scheduled", "Started", "Duration", "PID", "Result"];
+    let time = |t: chrono::DateTime<chrono::Utc>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+    let rows: Vec<Vec<String>> = runs
+        .iter()
+        .map(|r| {
+            vec![
+                r.scheduled_time.map(time).unwrap_or_else(|| "manual".to_string()),
+                time(r.start_time),
+                r.duration
+                    .map(|d| format!("{:.1}s", d))
+                    .unwrap_or_else(|| "N/A".to_string()),
+                r.pid.to_string(),
+                format_job_result(r),
+            ]
+        })
+        .collect();
+
+    print_table(&headers, &rows);
+}
+
+/// Print scheduled processes with their upcoming runs in the schedule's time zone
+pub fn print_schedule_table(schedules: &[ScheduleInfo]) {
+    if schedules.is_empty() {
+        println!("No scheduled processes");
+        return;
+    }
+
+    let headers = ["Name", "Schedule", "Time Zone", "Policy", "Next Runs", "Last Result"];
+    let rows: Vec<Vec<String>> = schedules
+        .iter()
+        .map(|info| {
+            let tz = info.schedule.tz().unwrap_or(chrono_tz::UTC);
+            let mut upcoming: Vec<String> = info
+                .upcoming
+                .iter()
+                .map(|t| t.with_timezone(&tz).format("%Y-%m-%d %H:%M:%S").to_string())
+                .collect();
+            // 只有待执行的那一次已经抽取了随机延迟
+            if let (Some(first), Some(delay)) = (upcoming.first_mut(), info.next_delay) {
+                first.push_str(&format!(" (+{}s)", delay));
+            }
+            let mut schedule = format_schedule(&info.schedule);
+            if let Some(ref resolved) = info.resolved_expression {
+                schedule.push_str(&format!(" → {}", resolved));
+            }
+            if let Some(jitter) = info.schedule.jitter {
+                schedule.push_str(&format!(" ±{}s", jitter));
+            }
+            vec![
+                info.name.clone(),
+                schedule,
+                tz.to_string(),
+                format!("{:?}", info.schedule.concurrency_policy).to_lowercase(),
+                if upcoming.is_empty() { "-".to_string() } else { upcoming.join(", ") },
+                info.last_run.as_ref().map(format_job_result).unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    print_table(&headers, &rows);
+}
+
+fn format_schedule(schedule: &Schedule) -> String {
+    match schedule.schedule_type {
+        ScheduleType::Cron => schedule.expression.clone().unwrap_or_default(),
+        ScheduleType::Interval => format!("every {}s", schedule.interval.unwrap_or(0)),
+    }
+}
+
+fn format_job_result(run: &JobRun) -> String {
+    if run.timed_out {
+        "⏱ Timed out".to_string()
+    } else if run.end_time.is_none() {
+        "Running...".to_string()
+    } else if run.succeeded() {
+        "✓ Success".to_string()
+    } else {
+        match run.exit_code {
+            Some(code) => format!("✗ Failed (code: {})", code),
+            None => "✗ Failed".to_string(),
+        }
+    }
+}
+
+/// Print a box-drawn table with the first column left aligned and the rest centered
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain(std::iter::once(h.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let line = |left: &str, mid: &str, right: &str| {
+        let parts: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{}{}{}", left, parts.join(mid), right)
+    };
+    let row = |cells: &[String]| {
+        let parts: Vec<String> = cells
             .iter()
-            .map(|s| format_memory(s.memory).len())
-            .max()
-            .unwrap_or(0),
-    );
-
-    let border = format!(
-        "┌{}┬{}┬{}┬{}┬{}┬{}┐",
-        "─".repeat(name_w),
-        "─".repeat(pid_w),
-        "─".repeat(status_w),
-        "─".repeat(uptime_w),
-        "─".repeat(cpu_w),
-        "─".repeat(memory_w)
-    );
-
-    println!("{}", border);
-    println!(
-        "│ {:^name_w$} │ {:^pid_w$} │ {:^status_w$} │ {:^uptime_w$} │ {:^cpu_w$} │ {:^memory_w$} │",
-        name_col,
-        pid_col,
-        status_col,
-        uptime_col,
-        cpu_col,
-        memory_col,
-        name_w = name_w,
-        pid_w = pid_w,
-        status_w = status_w,
-        uptime_w = uptime_w,
-        cpu_w = cpu_w,
-        memory_w = memory_w
-    );
-
-    let sep = format!(
-        "├{}┼{}┼{}┼{}┼{}┼{}┤",
-        "─".repeat(name_w),
-        "─".repeat(pid_w),
-        "─".repeat(status_w),
-        "─".repeat(uptime_w),
-        "─".repeat(cpu_w),
-        "─".repeat(memory_w)
-    );
-    println!("{}", sep);
-
-    for s in statuses {
-        let status_str = format_state(&s.state);
-        println!(
-            "│ {:name_w$} │ {:^pid_w$} │ {:^status_w$} │ {:^uptime_w$} │ {:^cpu_w$} │ {:^memory_w$} │",
-            s.name,
-            s.pid,
-            status_str,
-            format_uptime(s.uptime),
-            "N/A",
-            format_memory(s.memory),
-            name_w = name_w,
-            pid_w = pid_w,
-            status_w = status_w,
-            uptime_w = uptime_w,
-            cpu_w = cpu_w,
-            memory_w = memory_w
-        );
-    }
-
-    let footer = format!(
-        "└{}┴{}┴{}┴{}┴{}┴{}┘",
-        "─".repeat(name_w),
-        "─".repeat(pid_w),
-        "─".repeat(status_w),
-        "─".repeat(uptime_w),
-        "─".repeat(cpu_w),
-        "─".repeat(memory_w)
-    );
-    println!("{}", footer);
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (c, w))| {
+                if i == 0 {
+                    format!(" {:<w$} ", c, w = w)
+                } else {
+                    format!(" {:^w$} ", c, w = w)
+                }
+            })
+            .collect();
+        format!("│{}│", parts.join("│"))
+    };
+
+    println!("{}", line("┌", "┬", "┐"));
+    println!("{}", row(&headers.iter().map(|h| h.to_string()).collect::<Vec<_>>()));
+    println!("{}", line("├", "┼", "┤"));
+    for r in rows {
+        println!("{}", row(r));
+    }
+    println!("{}", line("└", "┴", "┘"));
 }
 
 /// Format process state to string
@@ -133,9 +326,35 @@ pub fn format_state(state: &ProcessState) -> String {
         ProcessState::Stopped => "stopped".to_string(),
         ProcessState::Dead => "dead".to_string(),
         ProcessState::Unknown => "unknown".to_string(),
+        ProcessState::Backoff => "backoff".to_string(),
+        ProcessState::Fatal => "fatal".to_string(),
+        ProcessState::Unhealthy => "unhealthy".to_string(),
+        ProcessState::Waiting => "waiting".to_string(),
+    }
+}
+
+/// Format PID, processes without a live PID show a dash
+pub fn format_pid(pid: u32) -> String {
+    if pid == 0 {
+        "-".to_string()
+    } else {
+        pid.to_string()
+    }
+}
+
+/// Format CPU percent
+pub fn format_cpu(cpu: Option<f64>) -> String {
+    match cpu {
+        Some(c) => format!("{:.1}%", c),
+        None => "N/A".to_string(),
     }
 }
 
+/// Format an optional count
+fn format_count(count: Option<u64>) -> String {
+    count.map_or("N/A".to_string(), |c| c.to_string())
+}
+
 /// Format uptime in human-readable format
 pub fn format_uptime(seconds: u64) -> String {
     if seconds < 60 {
@@ -161,3 +380,54 @@ pub fn format_memory(bytes: Option<u64>) -> String {
         None => "N/A".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_record_json() {
+        let config: ProcessConfig = toml::from_str(
+            r#"
+name = "report"
+command = "/usr/bin/php"
+args = ["report.php"]
+
+[schedule]
+type = "cron"
+expression = "0 0 3 * * *"
+"#,
+        )
+        .unwrap();
+        let status = ProcessStatus {
+            name: "report".to_string(),
+            pid: 0,
+            state: ProcessState::Stopped,
+            uptime: 0,
+            memory: None,
+            group_memory: None,
+            cpu: None,
+            threads: None,
+            fds: None,
+            restarts: 2,
+            next_run: None,
+            cgroup: None,
+            instance: None,
+        };
+
+        let json = serde_json::to_value(StatusRecord::new(&status, Some(&config))).unwrap();
+        assert_eq!(json["name"], "report");
+        assert_eq!(json["state"], "Stopped");
+        assert_eq!(json["restarts"], 2);
+        assert_eq!(json["command"], "/usr/bin/php");
+        assert_eq!(json["args"][0], "report.php");
+        assert_eq!(json["log_file"], "/var/log/mi7soft-report.log");
+        assert_eq!(json["schedule"]["expression"], "0 0 3 * * *");
+        assert!(json["next_run"].is_null());
+
+        // 没有对应配置时元数据字段仍然存在，值为 null
+        let json = serde_json::to_value(StatusRecord::new(&status, None)).unwrap();
+        assert!(json.as_object().unwrap().contains_key("command"));
+        assert!(json["command"].is_null());
+    }
+}
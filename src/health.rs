@@ -0,0 +1,251 @@
+//! 进程健康检查
+//!
+//! 进程存活不代表能正常服务：死锁、事件循环卡住等情况下 PID 依然存在。
+//! 健康检查按 `[processes.health_check]` 配置周期性探测，由守护进程主循环
+//! 调度；探测在独立任务中执行，结果通过事件通道回到主循环。
+
+use crate::config::{HealthCheck, HealthProbe};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+
+/// Run a single probe, returning a human readable reason on failure
+pub async fn run_probe(probe: &HealthProbe, timeout: Duration) -> Result<(), String> {
+    match tokio::time::timeout(timeout, probe_inner(probe)).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}s", timeout.as_secs_f64())),
+    }
+}
+
+async fn probe_inner(probe: &HealthProbe) -> Result<(), String> {
+    match probe {
+        HealthProbe::Http { host, port, path, expected_status } => {
+            let status = http_get_status(host, *port, path).await?;
+            if status == *expected_status {
+                Ok(())
+            } else {
+                Err(format!("HTTP status {} (expected {})", status, expected_status))
+            }
+        }
+        HealthProbe::Tcp { host, port } => TcpStream::connect((host.as_str(), *port))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("connect {}:{} failed: {}", host, port, e)),
+        HealthProbe::Exec { command, args } => {
+            let status = Command::new(command)
+                .args(args)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .kill_on_drop(true)
+                .status()
+                .await
+                .map_err(|e| format!("failed to run {}: {}", command, e))?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(format!("{} exited with {}", command, status))
+            }
+        }
+        HealthProbe::File { path, max_age } => {
+            let modified = std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .map_err(|e| format!("heartbeat file {}: {}", path, e))?;
+            let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+            if age.as_secs() <= *max_age {
+                Ok(())
+            } else {
+                Err(format!("heartbeat file {} is {}s old", path, age.as_secs()))
+            }
+        }
+    }
+}
+
+/// 最小化的 HTTP/1.0 GET，只解析状态行
+async fn http_get_status(host: &str, port: u16, path: &str) -> Result<u16, String> {
+    let mut stream = TcpStream::connect((host, port))
+        .await
+        .map_err(|e| format!("connect {}:{} failed: {}", host, port, e))?;
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}:{}\r\nUser-Agent: mi7soft-daemon\r\nConnection: close\r\n\r\n",
+        path, host, port
+    );
+    stream.write_all(request.as_bytes()).await.map_err(|e| e.to_string())?;
+
+    let mut buf = Vec::with_capacity(256);
+    let mut chunk = [0u8; 256];
+    while !buf.contains(&b'\n') {
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let line = String::from_utf8_lossy(&buf);
+    let status_line = line.lines().next().unwrap_or_default();
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("invalid HTTP response: {:?}", status_line))
+}
+
+/// 单个进程实例的健康检查状态
+#[derive(Debug, Default)]
+pub struct HealthState {
+    /// 连续失败次数
+    pub failures: u32,
+    /// 上一次发起检查的时间
+    pub last_probe: Option<Instant>,
+    /// 是否有检查正在进行
+    pub in_flight: bool,
+    /// 最近一次失败原因
+    pub last_error: Option<String>,
+    /// 自进程启动以来是否通过过检查，依赖它的进程在此之后才启动
+    pub passed: bool,
+}
+
+impl HealthState {
+    /// Whether a new probe should be started
+    pub fn is_due(&self, check: &HealthCheck, uptime: Duration, now: Instant) -> bool {
+        if self.in_flight || uptime < Duration::from_secs(check.start_period) {
+            return false;
+        }
+        self.last_probe
+            .is_none_or(|last| now.duration_since(last) >= Duration::from_secs(check.interval))
+    }
+
+    /// Record a probe result, returning true once the failure threshold is reached
+    pub fn on_result(&mut self, check: &HealthCheck, result: Result<(), String>) -> bool {
+        self.in_flight = false;
+        match result {
+            Ok(()) => {
+                self.failures = 0;
+                self.last_error = None;
+                self.passed = true;
+                false
+            }
+            Err(reason) => {
+                self.failures += 1;
+                self.last_error = Some(reason);
+                self.failures >= check.retries.max(1)
+            }
+        }
+    }
+
+    /// Whether consecutive failures have used up `retries`
+    pub fn is_unhealthy(&self, check: &HealthCheck) -> bool {
+        self.failures >= check.retries.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use tokio::net::TcpListener;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    async fn serve_once(response: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        port
+    }
+
+    fn http(port: u16) -> HealthProbe {
+        HealthProbe::Http {
+            host: "127.0.0.1".to_string(),
+            port,
+            path: "/health".to_string(),
+            expected_status: 200,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_probe() {
+        let port = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        assert!(run_probe(&http(port), TIMEOUT).await.is_ok());
+
+        let port = serve_once("HTTP/1.1 503 Service Unavailable\r\n\r\n").await;
+        let err = run_probe(&http(port), TIMEOUT).await.unwrap_err();
+        assert!(err.contains("503"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_probe_and_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let probe = HealthProbe::Tcp { host: "127.0.0.1".to_string(), port };
+        assert!(run_probe(&probe, TIMEOUT).await.is_ok());
+        drop(listener);
+        assert!(run_probe(&probe, TIMEOUT).await.is_err());
+
+        // 监听但从不响应的 HTTP 服务应当超时
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let err = run_probe(&http(port), Duration::from_millis(200)).await.unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+        drop(listener);
+    }
+
+    #[tokio::test]
+    async fn test_exec_and_file_probes() {
+        let ok = HealthProbe::Exec { command: "true".to_string(), args: vec![] };
+        let fail = HealthProbe::Exec { command: "false".to_string(), args: vec![] };
+        assert!(run_probe(&ok, TIMEOUT).await.is_ok());
+        assert!(run_probe(&fail, TIMEOUT).await.is_err());
+
+        let heartbeat = NamedTempFile::new().unwrap();
+        let path = heartbeat.path().to_string_lossy().to_string();
+        let fresh = HealthProbe::File { path: path.clone(), max_age: 60 };
+        assert!(run_probe(&fresh, TIMEOUT).await.is_ok());
+
+        let old = SystemTime::now() - Duration::from_secs(120);
+        heartbeat.as_file().set_modified(old).unwrap();
+        let stale = run_probe(&fresh, TIMEOUT).await.unwrap_err();
+        assert!(stale.contains("old"), "{}", stale);
+
+        let missing = HealthProbe::File { path: "/nonexistent/heartbeat".to_string(), max_age: 60 };
+        assert!(run_probe(&missing, TIMEOUT).await.is_err());
+    }
+
+    #[test]
+    fn test_state_threshold_and_start_period() {
+        let check = HealthCheck {
+            probe: HealthProbe::Tcp { host: "127.0.0.1".to_string(), port: 1 },
+            interval: 10,
+            timeout: 1,
+            retries: 2,
+            start_period: 30,
+        };
+        let mut state = HealthState::default();
+        let now = Instant::now();
+
+        assert!(!state.is_due(&check, Duration::from_secs(5), now));
+        assert!(state.is_due(&check, Duration::from_secs(30), now));
+
+        state.last_probe = Some(now);
+        assert!(!state.is_due(&check, Duration::from_secs(40), now + Duration::from_secs(5)));
+        assert!(state.is_due(&check, Duration::from_secs(40), now + Duration::from_secs(10)));
+
+        // 一次失败未用完 retries，仍然视为健康
+        assert!(!state.on_result(&check, Err("down".to_string())));
+        assert!(!state.is_unhealthy(&check));
+        assert!(!state.passed);
+        assert!(!state.on_result(&check, Ok(())));
+        assert!(!state.is_unhealthy(&check));
+        assert!(state.passed);
+        assert!(!state.on_result(&check, Err("down".to_string())));
+        assert!(state.on_result(&check, Err("down".to_string())));
+        assert!(state.is_unhealthy(&check));
+    }
+}
@@ -0,0 +1,251 @@
+//! 资源限制：rlimit 与 cgroup v2
+//!
+//! rlimit 在子进程 exec 之前设置；cgroup 限制通过在 `cgroup_parent` 下为每个进程
+//! 创建一个子 cgroup 实现，子进程在 exec 之前把自己写入 `cgroup.procs`。
+//! cgroup v2 不可用（未挂载、无写权限）时只记录警告，进程照常启动。
+
+use crate::config::ResourceLimits;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 默认的 cgroup 父目录
+pub const DEFAULT_CGROUP_PARENT: &str = "/sys/fs/cgroup/mi7soft-daemon";
+
+/// cgroup 的周期，cpu.max 按 "quota period" 写入
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Apply rlimits in the forked child; only async-signal-safe calls
+#[cfg(unix)]
+pub fn apply_rlimits(limits: &ResourceLimits) -> io::Result<()> {
+    use nix::sys::resource::{setrlimit, Resource};
+
+    let table = [
+        (Resource::RLIMIT_NOFILE, limits.nofile),
+        (Resource::RLIMIT_NPROC, limits.nproc),
+        (Resource::RLIMIT_CORE, limits.core),
+        (Resource::RLIMIT_AS, limits.address_space),
+    ];
+    for (resource, value) in table {
+        if let Some(value) = value {
+            setrlimit(resource, value, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// 单个进程 cgroup 的当前用量
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CgroupUsage {
+    pub memory_current: Option<u64>,
+    pub memory_max: Option<u64>,
+    pub pids_current: Option<u64>,
+    pub pids_max: Option<u64>,
+    /// 累计 CPU 时间（微秒）
+    pub cpu_usage_usec: Option<u64>,
+}
+
+/// 管理 `cgroup_parent` 下每个进程的 cgroup
+#[derive(Debug)]
+pub struct CgroupManager {
+    parent: PathBuf,
+    /// 首次使用时检测，None 表示尚未检测
+    available: Option<bool>,
+}
+
+impl CgroupManager {
+    pub fn new(parent: impl Into<PathBuf>) -> Self {
+        Self { parent: parent.into(), available: None }
+    }
+
+    /// Create or update the cgroup for `name`, returning its `cgroup.procs` for the child to join
+    pub fn prepare(&mut self, name: &str, limits: &ResourceLimits) -> Option<File> {
+        if !self.ensure_available() {
+            return None;
+        }
+        let dir = self.parent.join(name);
+        match create_cgroup(&dir, limits) {
+            Ok(procs) => Some(procs),
+            Err(e) => {
+                tracing::warn!(
+                    process = name,
+                    cgroup = %dir.display(),
+                    error = %e,
+                    "Failed to set up cgroup, starting without cgroup limits"
+                );
+                let _ = fs::remove_dir(&dir);
+                None
+            }
+        }
+    }
+
+    /// Remove the cgroup of a process that has no instances left (best effort)
+    pub fn remove(&self, name: &str) {
+        if self.available == Some(true) {
+            let _ = fs::remove_dir(self.parent.join(name));
+        }
+    }
+
+    /// Current usage of the cgroup for `name`, if it exists
+    pub fn usage(&self, name: &str) -> Option<CgroupUsage> {
+        let dir = self.parent.join(name);
+        if !dir.join("cgroup.procs").exists() {
+            return None;
+        }
+        Some(CgroupUsage {
+            memory_current: read_value(&dir, "memory.current"),
+            memory_max: read_value(&dir, "memory.max"),
+            pids_current: read_value(&dir, "pids.current"),
+            pids_max: read_value(&dir, "pids.max"),
+            cpu_usage_usec: fs::read_to_string(dir.join("cpu.stat")).ok().and_then(|stat| {
+                stat.lines()
+                    .find_map(|line| line.strip_prefix("usage_usec "))
+                    .and_then(|v| v.trim().parse().ok())
+            }),
+        })
+    }
+
+    fn ensure_available(&mut self) -> bool {
+        if let Some(available) = self.available {
+            return available;
+        }
+        let result = self.init_parent();
+        if let Err(ref e) = result {
+            tracing::warn!(
+                cgroup_parent = %self.parent.display(),
+                error = %e,
+                "cgroup v2 is not available, cgroup limits will not be applied"
+            );
+        }
+        self.available = Some(result.is_ok());
+        result.is_ok()
+    }
+
+    fn init_parent(&self) -> io::Result<()> {
+        let root = self.parent.parent().unwrap_or(Path::new("/"));
+        if !root.join("cgroup.controllers").exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a cgroup v2 directory", root.display()),
+            ));
+        }
+        fs::create_dir_all(&self.parent)?;
+        // 逐个下放控制器：某个控制器不可用时不影响其他控制器
+        for dir in [root, self.parent.as_path()] {
+            for controller in ["+memory", "+cpu", "+pids"] {
+                let _ = fs::write(dir.join("cgroup.subtree_control"), controller);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_cgroup(dir: &Path, limits: &ResourceLimits) -> io::Result<File> {
+    fs::create_dir_all(dir)?;
+    write_limit(dir, "memory.max", limits.memory_max.map(|v| v.to_string()))?;
+    write_limit(
+        dir,
+        "cpu.max",
+        limits.cpu_max.map(|cpus| format!("{} {}", (cpus * CPU_PERIOD_US as f64).round() as u64, CPU_PERIOD_US)),
+    )?;
+    write_limit(dir, "pids.max", limits.pids_max.map(|v| v.to_string()))?;
+    OpenOptions::new().write(true).open(dir.join("cgroup.procs"))
+}
+
+/// Write a limit, resetting it to "max" when it is no longer configured
+fn write_limit(dir: &Path, file: &str, value: Option<String>) -> io::Result<()> {
+    let path = dir.join(file);
+    match value {
+        Some(value) => fs::write(&path, value),
+        None if path.exists() => fs::write(&path, "max"),
+        None => Ok(()),
+    }
+}
+
+/// Read a single-value cgroup file; "max" reads as None
+fn read_value(dir: &Path, file: &str) -> Option<u64> {
+    fs::read_to_string(dir.join(file)).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn limits() -> ResourceLimits {
+        ResourceLimits {
+            memory_max: Some(512 * 1024 * 1024),
+            cpu_max: Some(1.5),
+            pids_max: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unavailable_degrades() {
+        let root = TempDir::new().unwrap();
+        let mut cgroups = CgroupManager::new(root.path().join("m7d"));
+        assert!(cgroups.prepare("web", &limits()).is_none());
+        assert_eq!(cgroups.available, Some(false));
+        assert!(!root.path().join("m7d").exists());
+    }
+
+    #[test]
+    fn test_prepare_writes_limits_and_reads_usage() {
+        // 用普通目录模拟 cgroup v2 文件系统
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join("cgroup.controllers"), "cpu memory pids").unwrap();
+        let dir = root.path().join("m7d").join("web");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cgroup.procs"), "").unwrap();
+        fs::write(dir.join("pids.max"), "100").unwrap();
+
+        let mut cgroups = CgroupManager::new(root.path().join("m7d"));
+        assert!(cgroups.prepare("web", &limits()).is_some());
+        assert_eq!(fs::read_to_string(dir.join("memory.max")).unwrap(), "536870912");
+        assert_eq!(fs::read_to_string(dir.join("cpu.max")).unwrap(), "150000 100000");
+        assert_eq!(fs::read_to_string(dir.join("pids.max")).unwrap(), "max");
+
+        fs::write(dir.join("memory.current"), "1048576\n").unwrap();
+        fs::write(dir.join("pids.current"), "3\n").unwrap();
+        fs::write(dir.join("cpu.stat"), "usage_usec 2500\nuser_usec 2000\n").unwrap();
+        let usage = cgroups.usage("web").unwrap();
+        assert_eq!(usage.memory_current, Some(1048576));
+        assert_eq!(usage.memory_max, Some(536870912));
+        assert_eq!(usage.pids_current, Some(3));
+        assert_eq!(usage.pids_max, None);
+        assert_eq!(usage.cpu_usage_usec, Some(2500));
+
+        assert!(cgroups.usage("other").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_rlimits_applied_in_child() {
+        use crate::config::ProcessConfig;
+        use crate::process::{ProcessEvent, ProcessManager};
+
+        let cfg: ProcessConfig = toml::from_str(
+            r#"
+name = "limited"
+command = "/bin/sh"
+args = ["-c", "test $(ulimit -n) = 256 && test $(ulimit -c) = 0"]
+capture_output = false
+
+[limits]
+nofile = 256
+core = 0
+"#,
+        )
+        .unwrap();
+
+        let mut pm = ProcessManager::new();
+        let mut events = pm.take_events().unwrap();
+        pm.spawn(&cfg).await.unwrap();
+        let Some(ProcessEvent::Exited { exit, .. }) = events.recv().await else {
+            panic!("expected exit event");
+        };
+        assert!(exit.success(), "{}", exit);
+    }
+}
@@ -1,22 +1,35 @@
+mod alert;
+mod api;
 mod cli;
 mod config;
+mod control;
 mod daemon;
 mod error;
 mod formatter;
+mod limits;
+mod health;
 mod logging;
+mod metrics;
 mod pidfile;
+#[cfg(unix)]
+mod privileges;
 mod process;
 mod process_output;
+mod procfs;
+mod restart;
 mod signal;
 mod storage;
+mod watchdog;
 
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ConfigAction, OutputFormat, ScheduleAction};
+use control::{ControlCommand, ControlResponse};
 use daemon::Daemon;
 use error::DaemonError;
-use formatter::print_status_table;
-use std::path::PathBuf;
-use tracing::{error, info};
+use formatter::{print_jobs_table, print_schedule_table, print_serialized, print_stats_table, print_status};
+use process_output::LogFilter;
+use std::path::{Path, PathBuf};
+use tracing::{debug, error, info};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -59,25 +72,52 @@ async fn run(cli: &Cli) -> Result<(), DaemonError> {
             run_daemon(config_path, pid_file_path, should_daemonize).await
         }
         Commands::StartProcess { name } => {
-            start_single_process(config_path, name).await
+            start_single_process(config_path, pid_file_path, name).await
         }
         Commands::Stop { name } => {
             stop_single_process(config_path, pid_file_path, name).await
         }
         Commands::Restart { name } => {
-            restart_single_process(config_path, name).await
+            restart_single_process(config_path, pid_file_path, name).await
         }
-        Commands::Status { name } => {
-            show_status(config_path, pid_file_path, name).await
+        Commands::Status { name, output } => {
+            show_status(config_path, pid_file_path, name, *output).await
         }
         Commands::Shutdown => {
             shutdown_daemon(pid_file_path).await
         }
-        Commands::Logs { name, lines, follow, since } => {
-            show_logs(config_path, name, *lines, *follow, *since).await
+        Commands::ReloadConfig => {
+            reload_daemon_config(pid_file_path).await
+        }
+        Commands::Reload { name } => {
+            reload_process(config_path, pid_file_path, name).await
+        }
+        Commands::Logs { name, lines, follow, since, until, stream, grep } => {
+            let filter = LogFilter {
+                since: *since,
+                until: *until,
+                stream: stream.map(Into::into),
+                pattern: grep.clone(),
+            };
+            show_logs(config_path, name, *lines, *follow, &filter).await
+        }
+        Commands::History { name, number, output } => {
+            show_history(config_path, name, *number, *output).await
+        }
+        Commands::Jobs { name, number, output } => {
+            show_jobs(name, *number, *output)
+        }
+        Commands::Stats { name } => {
+            show_stats(name).await
+        }
+        Commands::Schedule { action: ScheduleAction::List { count, output } } => {
+            show_schedules(config_path, pid_file_path, *count, *output).await
         }
-        Commands::History { name, number } => {
-            show_history(config_path, name, *number).await
+        Commands::Schedule { action: ScheduleAction::Run { name } } => {
+            run_job(pid_file_path, name).await
+        }
+        Commands::Config { action: ConfigAction::Check } => {
+            check_config(&config_path)
         }
     }
 }
@@ -105,15 +145,38 @@ async fn run_daemon(_config_path: PathBuf, _pid_file_path: &str, _daemonize: boo
     ))
 }
 
-async fn start_single_process(config_path: PathBuf, name: &str) -> Result<(), DaemonError> {
-    #[cfg(unix)]
-    let pid_file_path = "mi7soft-daemon-single.pid";
-
-    #[cfg(not(unix))]
-    let pid_file_path = "mi7soft-daemon-single.pid";
+/// Send a command to the running daemon over the control socket
+///
+/// Returns `Ok(None)` when no daemon is listening, in which case the caller
+/// falls back to operating on the state file directly.
+async fn send_to_daemon(
+    pid_file_path: &str,
+    command: ControlCommand,
+) -> Result<Option<ControlResponse>, DaemonError> {
+    let socket_path = control::socket_path(pid_file_path);
+    match control::send_command(&socket_path, command).await? {
+        Some(response) => response.into_result().map(Some),
+        None => {
+            debug!(socket = ?socket_path, "No daemon listening, running offline");
+            Ok(None)
+        }
+    }
+}
 
-    let mut daemon = Daemon::new(config_path, pid_file_path)?;
-    let pid = daemon.start_process(name).await?;
+async fn start_single_process(
+    config_path: PathBuf,
+    pid_file_path: &str,
+    name: &str,
+) -> Result<(), DaemonError> {
+    let command = ControlCommand::Start { name: name.to_string() };
+    let pid = match send_to_daemon(pid_file_path, command).await? {
+        Some(ControlResponse::Started { pid }) => pid,
+        Some(other) => return Err(unexpected_response(other)),
+        None => {
+            let mut daemon = Daemon::new(config_path, "mi7soft-daemon-single.pid")?;
+            daemon.start_process(name).await?
+        }
+    };
     info!(process = name, pid = pid, "Process started");
     println!("Started process {} with PID {}", name, pid);
     Ok(())
@@ -124,8 +187,15 @@ async fn stop_single_process(
     pid_file_path: &str,
     name: &str,
 ) -> Result<(), DaemonError> {
-    let mut daemon = Daemon::new(config_path, pid_file_path)?;
-    let stopped = daemon.stop_process(name).await?;
+    let command = ControlCommand::Stop { name: name.to_string() };
+    let stopped = match send_to_daemon(pid_file_path, command).await? {
+        Some(ControlResponse::Stopped { pids }) => pids,
+        Some(other) => return Err(unexpected_response(other)),
+        None => {
+            let mut daemon = Daemon::new(config_path, pid_file_path)?;
+            daemon.stop_process(name).await?
+        }
+    };
     info!(process = name, instances = stopped.len(), "Process stopped");
     println!("Stopped process {}: {} instance(s)", name, stopped.len());
     for pid in stopped {
@@ -134,15 +204,20 @@ async fn stop_single_process(
     Ok(())
 }
 
-async fn restart_single_process(config_path: PathBuf, name: &str) -> Result<(), DaemonError> {
-    #[cfg(unix)]
-    let pid_file_path = "mi7soft-daemon-single.pid";
-
-    #[cfg(not(unix))]
-    let pid_file_path = "mi7soft-daemon-single.pid";
-
-    let mut daemon = Daemon::new(config_path, pid_file_path)?;
-    let pids = daemon.restart_process(name).await?;
+async fn restart_single_process(
+    config_path: PathBuf,
+    pid_file_path: &str,
+    name: &str,
+) -> Result<(), DaemonError> {
+    let command = ControlCommand::Restart { name: name.to_string() };
+    let pids = match send_to_daemon(pid_file_path, command).await? {
+        Some(ControlResponse::Restarted { pids }) => pids,
+        Some(other) => return Err(unexpected_response(other)),
+        None => {
+            let mut daemon = Daemon::new(config_path, "mi7soft-daemon-single.pid")?;
+            daemon.restart_process(name).await?
+        }
+    };
     info!(process = name, instances = pids.len(), "Process restarted");
     println!("Restarted process {} with {} instance(s)", name, pids.len());
     for pid in pids {
@@ -155,28 +230,153 @@ async fn show_status(
     config_path: PathBuf,
     pid_file_path: &str,
     name: &Option<String>,
+    output: OutputFormat,
 ) -> Result<(), DaemonError> {
-    let daemon = Daemon::new_read_only(config_path, pid_file_path)?;
+    let command = ControlCommand::Status { name: name.clone() };
+    let statuses = match send_to_daemon(pid_file_path, command).await? {
+        Some(ControlResponse::Status { processes }) => processes,
+        Some(other) => return Err(unexpected_response(other)),
+        None => {
+            let daemon = Daemon::new_read_only(config_path.clone(), pid_file_path)?;
+            match name {
+                Some(process_name) => daemon.get_status(process_name)?,
+                None => daemon.get_all_status(),
+            }
+        }
+    };
 
-    if let Some(process_name) = name {
-        let statuses = daemon.get_status(process_name)?;
-        info!(process = process_name, "Showing status");
-        print_status_table(&statuses);
-    } else {
-        let all_statuses = daemon.get_all_status();
-        info!("Showing all process status");
-        print_status_table(&all_statuses);
+    info!(process = ?name, "Showing status");
+    // 配置元数据（命令行、日志文件、调度）来自本地配置文件，读取失败时只输出运行状态
+    let configs = crate::config::load_config(&config_path)
+        .map(|c| c.processes)
+        .unwrap_or_default();
+    print_status(&statuses, &configs, output)
+}
+
+async fn reload_daemon_config(pid_file_path: &str) -> Result<(), DaemonError> {
+    match send_to_daemon(pid_file_path, ControlCommand::Reload).await? {
+        Some(ControlResponse::Reloaded) => {
+            println!("Configuration reloaded");
+            Ok(())
+        }
+        Some(other) => Err(unexpected_response(other)),
+        None => Err(DaemonError::Control("Daemon is not running".to_string())),
     }
+}
+
+async fn reload_process(
+    config_path: PathBuf,
+    pid_file_path: &str,
+    name: &str,
+) -> Result<(), DaemonError> {
+    let command = ControlCommand::ReloadProcess { name: name.to_string() };
+    let pids = match send_to_daemon(pid_file_path, command).await? {
+        Some(ControlResponse::ProcessReloaded { pids }) => pids,
+        Some(other) => return Err(unexpected_response(other)),
+        None => {
+            let daemon = Daemon::new_read_only(config_path, pid_file_path)?;
+            daemon.reload_process(name)?
+        }
+    };
+    info!(process = name, instances = pids.len(), "Reload signal sent");
+    println!("Sent reload signal to process {}: {} instance(s)", name, pids.len());
+    for pid in pids {
+        println!("  - PID {}", pid);
+    }
+    Ok(())
+}
+
+async fn show_schedules(
+    config_path: PathBuf,
+    pid_file_path: &str,
+    count: usize,
+    output: OutputFormat,
+) -> Result<(), DaemonError> {
+    let schedules = match send_to_daemon(pid_file_path, ControlCommand::Schedules { count }).await? {
+        Some(ControlResponse::Schedules { schedules }) => schedules,
+        Some(other) => return Err(unexpected_response(other)),
+        None => {
+            // 离线时按配置计算，最近一次结果直接读取历史数据库
+            use crate::storage::{default_db_path, Storage};
+            let daemon = Daemon::new_read_only(config_path, pid_file_path)?;
+            let mut schedules = daemon.schedules(count);
+            if let Ok(storage) = Storage::new(default_db_path()) {
+                for info in &mut schedules {
+                    info.last_run = storage.get_job_runs(&info.name, 1).ok().and_then(|runs| runs.into_iter().next());
+                }
+            }
+            schedules
+        }
+    };
 
+    if matches!(output, OutputFormat::Json | OutputFormat::Yaml) {
+        return print_serialized(&schedules, output);
+    }
+    print_schedule_table(&schedules);
     Ok(())
 }
 
+async fn run_job(pid_file_path: &str, name: &str) -> Result<(), DaemonError> {
+    let command = ControlCommand::RunJob { name: name.to_string() };
+    match send_to_daemon(pid_file_path, command).await? {
+        Some(ControlResponse::Started { pid }) => {
+            info!(process = name, pid = pid, "Manual run started");
+            println!("Started run of {} with PID {}", name, pid);
+            Ok(())
+        }
+        Some(other) => Err(unexpected_response(other)),
+        None => Err(DaemonError::Control("Daemon is not running".to_string())),
+    }
+}
+
+fn check_config(config_path: &Path) -> Result<(), DaemonError> {
+    let config = crate::config::load_config(config_path)?;
+    let problems = crate::config::check_environment(&config);
+    if problems.is_empty() {
+        println!("Configuration OK: {} ({} processes)", config_path.display(), config.processes.len());
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("  ✗ {}", problem);
+    }
+    Err(DaemonError::Config(format!(
+        "{} problem(s) found in {}", problems.len(), config_path.display()
+    )))
+}
+
 async fn shutdown_daemon(pid_file_path: &str) -> Result<(), DaemonError> {
+    info!("Shutdown requested");
+
+    match send_to_daemon(pid_file_path, ControlCommand::Shutdown).await? {
+        Some(ControlResponse::ShuttingDown) => {
+            println!("Stopping daemon...");
+            // 守护进程退出时会删除 PID 文件
+            for _ in 0..100 {
+                if !Path::new(pid_file_path).exists() {
+                    println!("Daemon stopped");
+                    return Ok(());
+                }
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            }
+            Err(DaemonError::Control(
+                "Daemon did not exit within 10 seconds".to_string(),
+            ))
+        }
+        Some(other) => Err(unexpected_response(other)),
+        None => kill_daemon(pid_file_path).await,
+    }
+}
+
+fn unexpected_response(response: ControlResponse) -> DaemonError {
+    DaemonError::Control(format!("Unexpected response from daemon: {:?}", response))
+}
+
+/// Fallback when no daemon answers on the control socket
+async fn kill_daemon(pid_file_path: &str) -> Result<(), DaemonError> {
     use std::fs;
     use std::process::Command;
 
-    info!("Shutdown requested");
-
     let pid = fs::read_to_string(pid_file_path)
         .map_err(|e| DaemonError::PidFile(format!("Failed to read PID file: {}", e)))?
         .trim()
@@ -225,37 +425,51 @@ async fn shutdown_daemon(pid_file_path: &str) -> Result<(), DaemonError> {
 async fn show_logs(
     config_path: PathBuf,
     name: &str,
-    lines: usize,
+    lines: Option<usize>,
     follow: bool,
-    since: Option<i64>,
+    filter: &LogFilter,
 ) -> Result<(), DaemonError> {
     use crate::process_output::LogViewer;
 
     let config = crate::config::load_config(&config_path)?;
+    // "worker:1" 查看 1 号实例的日志
+    let (process_name, instance) = if config.processes.iter().any(|p| p.name == name) {
+        (name, None)
+    } else {
+        crate::config::split_instance(name)
+    };
     let process_config = config
         .processes
         .iter()
-        .find(|p| p.name == name)
+        .find(|p| p.name == process_name)
         .ok_or_else(|| DaemonError::Config(format!("Process '{}' not found", name)))?;
 
-    let log_file = process_config.log_file.as_ref()
+    let log_file = process_config.for_instance(instance.unwrap_or(0)).log_path()
         .ok_or_else(|| DaemonError::Config("No log file configured".to_string()))?;
 
     let viewer = LogViewer::new(std::path::PathBuf::from(log_file));
 
     if follow {
         println!("Following logs for {} (Ctrl+C to stop)...", name);
-        let mut receiver = viewer.follow().await?;
+        let mut receiver = viewer.follow(lines.unwrap_or(100)).await?;
         while let Some(line) = receiver.recv().await {
-            println!("{}", line);
+            if filter.matches(&line) {
+                println!("{}", line);
+            }
         }
-    } else if let Some(secs) = since {
-        let logs = viewer.since(secs).await?;
+    } else if !filter.is_empty() {
+        // 按时间窗口过滤时默认不限制行数
+        let limit = lines.or(if filter.since.is_some() || filter.until.is_some() {
+            None
+        } else {
+            Some(100)
+        });
+        let logs = viewer.filter(filter, limit).await?;
         for line in logs {
             println!("{}", line);
         }
     } else {
-        let logs = viewer.tail(lines).await?;
+        let logs = viewer.tail(lines.unwrap_or(100)).await?;
         for line in logs {
             println!("{}", line);
         }
@@ -264,17 +478,16 @@ async fn show_logs(
     Ok(())
 }
 
-async fn show_history(_config_path: PathBuf, name: &str, number: usize) -> Result<(), DaemonError> {
-    use crate::storage::Storage;
-
-    let db_path = dirs::data_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("mi7soft-daemon")
-        .join("daemon.db");
+async fn show_history(_config_path: PathBuf, name: &str, number: usize, output: OutputFormat) -> Result<(), DaemonError> {
+    use crate::storage::{default_db_path, Storage};
 
-    let storage = Storage::new(db_path)?;
+    let storage = Storage::new(default_db_path())?;
     let history = storage.get_history(name, number)?;
 
+    if matches!(output, OutputFormat::Json | OutputFormat::Yaml) {
+        return print_serialized(&history, output);
+    }
+
     if history.is_empty() {
         println!("No history found for process: {}", name);
         return Ok(());
@@ -287,12 +500,19 @@ async fn show_history(_config_path: PathBuf, name: &str, number: usize) -> Resul
     println!("{:-<80}", "");
 
     for record in history {
-        let status = match record.exit_code {
-            Some(0) => "✓ Success",
-            Some(code) => &format!("✗ Failed (code: {})", code),
-            None => "Running...",
+        let status = match (record.exit_code, &record.end_time) {
+            (Some(0), _) => "✓ Success".to_string(),
+            (Some(code), _) => format!("✗ Failed (code: {})", code),
+            (None, Some(_)) => "Ended".to_string(),
+            (None, None) => "Running...".to_string(),
         };
 
+        let reason = format!(
+            "{} → {}",
+            record.start_reason.as_deref().unwrap_or("-"),
+            record.end_reason.as_deref().unwrap_or("-")
+        );
+
         let duration = match record.end_time {
             Some(end) => {
                 let secs = (end - record.start_time).num_seconds();
@@ -302,16 +522,50 @@ async fn show_history(_config_path: PathBuf, name: &str, number: usize) -> Resul
         };
 
         println!(
-            "  PID {:<6} | {:<19} - {:<19} | {:<12} | {}",
+            "  PID {:<6} | {:<19} - {:<19} | {:<12} | {:<20} | {}",
             record.pid,
             record.start_time.format("%Y-%m-%d %H:%M:%S"),
             record.end_time
                 .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
                 .unwrap_or_else(|| "Still running".to_string()),
             duration,
-            status
+            status,
+            reason
         );
     }
 
     Ok(())
 }
+
+fn show_jobs(name: &str, number: usize, output: OutputFormat) -> Result<(), DaemonError> {
+    use crate::storage::{default_db_path, Storage};
+
+    let storage = Storage::new(default_db_path())?;
+    let runs = storage.get_job_runs(name, number)?;
+
+    if matches!(output, OutputFormat::Json | OutputFormat::Yaml) {
+        return print_serialized(&runs, output);
+    }
+
+    if runs.is_empty() {
+        println!("No job runs found for process: {}", name);
+        return Ok(());
+    }
+
+    println!("Runs for job {} (last {} runs):", name, number);
+    print_jobs_table(&runs);
+    Ok(())
+}
+
+async fn show_stats(name: &Option<String>) -> Result<(), DaemonError> {
+    use crate::storage::{default_db_path, Storage};
+
+    let storage = Storage::new(default_db_path())?;
+    let stats = match name {
+        Some(process_name) => storage.get_stats(process_name)?.into_iter().collect(),
+        None => storage.get_all_stats()?,
+    };
+
+    print_stats_table(&stats);
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! Prometheus 指标
+//!
+//! 配置了 `[daemon] metrics_addr` 时守护进程额外监听一个 HTTP 端口，`GET /metrics`
+//! 与控制套接字一样通过请求通道交给 `Daemon::run` 主循环，由主循环从进程注册表和
+//! 调度器生成文本格式（text/plain; version=0.0.4）的指标。
+
+use crate::control::{self, ControlCommand, ControlResponse, PendingRequest};
+use crate::error::DaemonError;
+use crate::process::{JobStats, ProcessState, ProcessStatus};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 单个配置进程的指标，多个实例按进程名聚合
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessMetrics {
+    pub name: String,
+    /// 正在运行的实例数
+    pub instances: u64,
+    /// 最早启动的实例的运行时间（秒）
+    pub uptime: u64,
+    /// 所有实例进程组的 RSS 之和
+    pub memory: u64,
+    pub cpu: f64,
+    pub restarts: u64,
+    pub last_exit_code: Option<i32>,
+}
+
+impl ProcessMetrics {
+    /// Aggregate the status rows that belong to `name`
+    pub fn collect(name: &str, statuses: &[ProcessStatus]) -> Self {
+        let mut metrics = Self { name: name.to_string(), ..Default::default() };
+        for status in statuses.iter().filter(|s| s.name == name) {
+            metrics.restarts = metrics.restarts.max(status.restarts as u64);
+            if status.pid == 0 || !matches!(status.state, ProcessState::Running | ProcessState::Unhealthy) {
+                continue;
+            }
+            metrics.instances += 1;
+            metrics.uptime = metrics.uptime.max(status.uptime);
+            metrics.memory += status.group_memory.or(status.memory).unwrap_or(0);
+            metrics.cpu += status.cpu.unwrap_or(0.0);
+        }
+        metrics
+    }
+}
+
+/// Render process and job metrics in the Prometheus text format
+pub fn render(processes: &[ProcessMetrics], jobs: &[(String, JobStats)]) -> String {
+    let mut out = String::new();
+
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(&str, String)>| {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for (label, value) in samples {
+            let _ = writeln!(out, "{}{{process=\"{}\"}} {}", name, escape_label(label), value);
+        }
+    };
+
+    let each = |f: fn(&ProcessMetrics) -> Option<String>| -> Vec<(&str, String)> {
+        processes.iter().filter_map(|p| Some((p.name.as_str(), f(p)?))).collect()
+    };
+    family("m7d_process_up", "gauge", "Whether at least one instance of the process is running.",
+        each(|p| Some(u8::from(p.instances > 0).to_string())));
+    family("m7d_process_instances", "gauge", "Number of running instances.",
+        each(|p| Some(p.instances.to_string())));
+    family("m7d_process_uptime_seconds", "gauge", "Uptime of the oldest running instance.",
+        each(|p| Some(p.uptime.to_string())));
+    family("m7d_process_memory_rss_bytes", "gauge", "Resident set size of the process groups.",
+        each(|p| Some(p.memory.to_string())));
+    family("m7d_process_cpu_percent", "gauge", "CPU usage in percent of one core.",
+        each(|p| Some(format!("{:.2}", p.cpu))));
+    family("m7d_process_restarts_total", "counter", "Restarts since the daemon started.",
+        each(|p| Some(p.restarts.to_string())));
+    family("m7d_process_last_exit_code", "gauge", "Exit code of the last instance that exited (128 + signal if killed).",
+        each(|p| p.last_exit_code.map(|c| c.to_string())));
+
+    let jobs_each = |f: fn(&JobStats) -> Option<String>| -> Vec<(&str, String)> {
+        jobs.iter().filter_map(|(name, stats)| Some((name.as_str(), f(stats)?))).collect()
+    };
+    family("m7d_job_runs_total", "counter", "Scheduled runs started.",
+        jobs_each(|j| Some(j.runs.to_string())));
+    family("m7d_job_failures_total", "counter", "Scheduled runs that exited with a non-zero status.",
+        jobs_each(|j| Some(j.failures.to_string())));
+    family("m7d_job_last_duration_seconds", "gauge", "Duration of the last finished run.",
+        jobs_each(|j| j.last_duration.map(|d| d.as_secs_f64().to_string())));
+    family("m7d_job_last_exit_code", "gauge", "Exit code of the last finished run.",
+        jobs_each(|j| j.last_exit_code.map(|c| c.to_string())));
+
+    // summary 只输出 _sum 与 _count，不计算分位数
+    let _ = writeln!(out, "# HELP m7d_job_duration_seconds Duration of finished runs.");
+    let _ = writeln!(out, "# TYPE m7d_job_duration_seconds summary");
+    for (name, stats) in jobs {
+        let label = escape_label(name);
+        let _ = writeln!(out, "m7d_job_duration_seconds_sum{{process=\"{}\"}} {}", label, stats.duration_sum.as_secs_f64());
+        let _ = writeln!(out, "m7d_job_duration_seconds_count{{process=\"{}\"}} {}", label, stats.completed);
+    }
+
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Bind the metrics listener and serve `/metrics` in the background
+pub async fn serve(addr: &str, requests: mpsc::UnboundedSender<PendingRequest>) -> Result<SocketAddr, DaemonError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| DaemonError::Control(format!("Failed to bind metrics listener on {}: {}", addr, e)))?;
+    let local_addr = listener.local_addr()?;
+
+    let app = Router::new().route("/metrics", get(scrape)).with_state(requests);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!(error = %e, "Metrics listener stopped");
+        }
+    });
+
+    info!(addr = %local_addr, "Metrics listening");
+    Ok(local_addr)
+}
+
+async fn scrape(State(requests): State<mpsc::UnboundedSender<PendingRequest>>) -> impl IntoResponse {
+    match control::dispatch(&requests, ControlCommand::Metrics).await {
+        Some(ControlResponse::Metrics { text }) => (StatusCode::OK, [(header::CONTENT_TYPE, CONTENT_TYPE)], text),
+        _ => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            "daemon is not responding\n".to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn status(name: &str, pid: u32, state: ProcessState) -> ProcessStatus {
+        ProcessStatus {
+            name: name.to_string(),
+            pid,
+            state,
+            uptime: 120,
+            memory: Some(1024),
+            group_memory: Some(4096),
+            cpu: Some(12.5),
+            threads: None,
+            fds: None,
+            restarts: 2,
+            next_run: None,
+            cgroup: None,
+            instance: None,
+        }
+    }
+
+    fn sample() -> String {
+        let statuses = vec![
+            status("web", 10, ProcessState::Running),
+            status("web", 11, ProcessState::Unhealthy),
+            status("worker", 0, ProcessState::Fatal),
+        ];
+        let mut worker = ProcessMetrics::collect("worker", &statuses);
+        worker.last_exit_code = Some(137);
+        let processes = vec![ProcessMetrics::collect("web", &statuses), worker];
+
+        let mut stats = JobStats { runs: 3, ..Default::default() };
+        stats.record_completion(Duration::from_secs(2), Some(0));
+        stats.record_completion(Duration::from_secs(4), Some(1));
+        render(&processes, &[("backup".to_string(), stats)])
+    }
+
+    #[test]
+    fn test_render() {
+        let text = sample();
+        for line in [
+            "# TYPE m7d_process_up gauge",
+            "m7d_process_up{process=\"web\"} 1",
+            "m7d_process_up{process=\"worker\"} 0",
+            "m7d_process_instances{process=\"web\"} 2",
+            "m7d_process_memory_rss_bytes{process=\"web\"} 8192",
+            "m7d_process_cpu_percent{process=\"web\"} 25.00",
+            "m7d_process_restarts_total{process=\"worker\"} 2",
+            "m7d_process_last_exit_code{process=\"worker\"} 137",
+            "m7d_job_runs_total{process=\"backup\"} 3",
+            "m7d_job_failures_total{process=\"backup\"} 1",
+            "m7d_job_last_duration_seconds{process=\"backup\"} 4",
+            "m7d_job_duration_seconds_sum{process=\"backup\"} 6",
+            "m7d_job_duration_seconds_count{process=\"backup\"} 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in\n{}", line, text);
+        }
+        assert!(!text.contains("m7d_process_last_exit_code{process=\"web\"}"));
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[tokio::test]
+    async fn test_scrape() {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<PendingRequest>();
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                assert_eq!(request.command, ControlCommand::Metrics);
+                request.respond(ControlResponse::Metrics { text: sample() });
+            }
+        });
+        let addr = serve("127.0.0.1:0", sender).await.unwrap();
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("text/plain; version=0.0.4"), "{}", response);
+        assert!(response.contains("m7d_job_runs_total{process=\"backup\"} 3"), "{}", response);
+    }
+}
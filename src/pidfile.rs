@@ -71,6 +71,7 @@ impl PidFile {
     }
 
     /// Write current PID to file
+    #[cfg(not(unix))]
     fn write_pid(&mut self) -> Result<(), DaemonError> {
         let pid = std::process::id();
 
@@ -102,15 +103,22 @@ impl PidFile {
         use std::os::unix::fs::OpenOptionsExt;
 
         // Check if process already running
-        if let Some(pid) = self.read_pid()? {
-            if self.is_process_alive(pid) {
-                return Err(DaemonError::AlreadyRunning {
-                    name: "daemon".to_string(),
-                    pid,
-                });
+        match self.read_pid() {
+            Ok(Some(pid)) => {
+                if self.is_process_alive(pid) {
+                    return Err(DaemonError::AlreadyRunning {
+                        name: "daemon".to_string(),
+                        pid,
+                    });
+                }
+                // Process died, clean up stale lock
+                let _ = std::fs::remove_file(&self.path);
+            }
+            // 文件不存在，或为空（另一个实例刚创建、尚未写入 PID），交给 O_EXCL 判断
+            Ok(None) => {}
+            Err(e) => {
+                return Err(DaemonError::LockFile(format!("Unreadable lock file {}: {}", self.path, e)));
             }
-            // Process died, clean up stale lock
-            let _ = std::fs::remove_file(&self.path);
         }
 
         // Create file with exclusive lock (O_CREAT | O_EXCL)
@@ -173,7 +181,7 @@ impl PidFile {
     /// Check if process is still running
     #[cfg(unix)]
     fn is_process_alive(&self, pid: u32) -> bool {
-        use nix::sys::signal::{kill, Signal};
+        use nix::sys::signal::kill;
         use nix::unistd::Pid;
 
         // Send signal 0 - no signal is actually sent, just check if process exists
@@ -197,12 +205,17 @@ impl Drop for PidFile {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::NamedTempFile;
+    use tempfile::TempDir;
+
+    /// PID file path that does not exist yet
+    fn lock_path(dir: &TempDir) -> String {
+        dir.path().join("daemon.pid").to_str().unwrap().to_string()
+    }
 
     #[test]
     fn test_pidfile_creation() {
-        let temp_file = NamedTempFile::new().unwrap();
-        let path = temp_file.path().to_str().unwrap().to_string();
+        let dir = tempfile::tempdir().unwrap();
+        let path = lock_path(&dir);
 
         let mut pidfile = PidFile::new(&path);
         pidfile.acquire_lock().unwrap();
@@ -214,8 +227,8 @@ mod tests {
 
     #[test]
     fn test_pidfile_duplicate() {
-        let temp_file = NamedTempFile::new().unwrap();
-        let path = temp_file.path().to_str().unwrap().to_string();
+        let dir = tempfile::tempdir().unwrap();
+        let path = lock_path(&dir);
 
         let mut pidfile1 = PidFile::new(&path);
         pidfile1.acquire_lock().unwrap();
@@ -227,8 +240,8 @@ mod tests {
 
     #[test]
     fn test_pidfile_release() {
-        let temp_file = NamedTempFile::new().unwrap();
-        let path = temp_file.path().to_str().unwrap().to_string();
+        let dir = tempfile::tempdir().unwrap();
+        let path = lock_path(&dir);
 
         let mut pidfile = PidFile::new(&path);
         pidfile.acquire_lock().unwrap();
@@ -236,4 +249,21 @@ mod tests {
 
         assert!(!Path::new(&path).exists());
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pidfile_empty_lock_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = lock_path(&dir);
+        std::fs::write(&path, "").unwrap();
+
+        // 空文件可能属于正在启动的另一个实例，不能删除
+        let mut pidfile = PidFile::new(&path);
+        assert!(matches!(pidfile.acquire_lock(), Err(DaemonError::LockFile(_))));
+        assert!(Path::new(&path).exists());
+
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(matches!(pidfile.acquire_lock(), Err(DaemonError::LockFile(_))));
+        assert!(Path::new(&path).exists());
+    }
 }
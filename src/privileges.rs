@@ -0,0 +1,181 @@
+//! 子进程降权
+//!
+//! 守护进程通常以 root 运行（需要写 `/var/run`、`/var/log`），子进程默认继承 root。
+//! 配置了 `user`/`group`/`supplementary_groups`/`umask` 的进程在 fork 之后、exec 之前
+//! 依次设置附加组、GID、UID 和 umask。
+
+use crate::config::ProcessConfig;
+use crate::error::DaemonError;
+use nix::sys::stat::{umask, Mode};
+use nix::unistd::{getgrouplist, setgid, setgroups, setuid, Gid, Group, Uid, User};
+use std::ffi::CString;
+
+/// 子进程的运行身份
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    /// 目标用户，未配置 user 时为 None（保持当前用户）
+    pub user: Option<User>,
+    pub gid: Option<Gid>,
+    pub groups: Option<Vec<Gid>>,
+    pub umask: Option<Mode>,
+}
+
+impl Credentials {
+    /// Resolve names from the config; `None` when the process keeps the daemon's credentials
+    pub fn resolve(config: &ProcessConfig) -> Result<Option<Self>, DaemonError> {
+        let err = |msg: String| DaemonError::Config(format!("Process '{}': {}", config.name, msg));
+
+        if config.user.is_none()
+            && config.group.is_none()
+            && config.supplementary_groups.is_empty()
+            && config.umask.is_none()
+        {
+            return Ok(None);
+        }
+
+        let user = config.user.as_deref().map(lookup_user).transpose().map_err(err)?;
+
+        let gid = match config.group.as_deref() {
+            Some(group) => Some(lookup_group(group).map_err(err)?),
+            None => user.as_ref().map(|u| u.gid),
+        };
+
+        let groups = if !config.supplementary_groups.is_empty() {
+            let groups = config.supplementary_groups
+                .iter()
+                .map(|g| lookup_group(g))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(err)?;
+            Some(groups)
+        } else if let (Some(user), Some(gid)) = (&user, gid) {
+            let name = CString::new(user.name.as_str())
+                .map_err(|_| err(format!("invalid user name '{}'", user.name)))?;
+            let groups = getgrouplist(&name, gid)
+                .map_err(|e| err(format!("failed to list groups of '{}': {}", user.name, e)))?;
+            Some(groups)
+        } else {
+            None
+        };
+
+        let umask = config.umask.as_deref().map(parse_umask).transpose().map_err(err)?;
+
+        Ok(Some(Self { user, gid, groups, umask }))
+    }
+
+    /// Apply the credentials in the forked child; only async-signal-safe calls
+    pub fn apply(&self) -> std::io::Result<()> {
+        if let Some(ref groups) = self.groups {
+            setgroups(groups)?;
+        }
+        if let Some(gid) = self.gid {
+            setgid(gid)?;
+        }
+        if let Some(ref user) = self.user {
+            setuid(user.uid)?;
+        }
+        if let Some(mode) = self.umask {
+            umask(mode);
+        }
+        Ok(())
+    }
+
+    /// Login environment (HOME/USER/LOGNAME) for the target user
+    pub fn environment(&self) -> Vec<(&'static str, String)> {
+        match self.user {
+            Some(ref user) => vec![
+                ("HOME", user.dir.to_string_lossy().to_string()),
+                ("USER", user.name.clone()),
+                ("LOGNAME", user.name.clone()),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+fn lookup_user(spec: &str) -> Result<User, String> {
+    let found = match spec.parse::<u32>() {
+        Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+        Err(_) => User::from_name(spec),
+    };
+    found
+        .map_err(|e| format!("failed to look up user '{}': {}", spec, e))?
+        .ok_or_else(|| format!("unknown user '{}'", spec))
+}
+
+fn lookup_group(spec: &str) -> Result<Gid, String> {
+    if let Ok(gid) = spec.parse::<u32>() {
+        return Ok(Gid::from_raw(gid));
+    }
+    Group::from_name(spec)
+        .map_err(|e| format!("failed to look up group '{}': {}", spec, e))?
+        .map(|g| g.gid)
+        .ok_or_else(|| format!("unknown group '{}'", spec))
+}
+
+fn parse_umask(spec: &str) -> Result<Mode, String> {
+    u32::from_str_radix(spec.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .map(|mode| Mode::from_bits_truncate(mode as nix::libc::mode_t))
+        .ok_or_else(|| format!("invalid umask '{}', expected an octal value such as \"022\"", spec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{ProcessEvent, ProcessManager};
+
+    fn config(extra: &str) -> ProcessConfig {
+        toml::from_str(&format!("name = \"p\"\ncommand = \"/bin/true\"\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(Credentials::resolve(&config("")).unwrap(), None);
+
+        let creds = Credentials::resolve(&config("user = \"root\"")).unwrap().unwrap();
+        assert_eq!(creds.user.as_ref().unwrap().uid, Uid::from_raw(0));
+        assert_eq!(creds.gid, Some(Gid::from_raw(0)));
+        assert!(creds.groups.as_ref().unwrap().contains(&Gid::from_raw(0)));
+
+        let creds = Credentials::resolve(&config(
+            "user = \"0\"\ngroup = \"1234\"\nsupplementary_groups = [\"1235\"]\numask = \"027\"",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(creds.gid, Some(Gid::from_raw(1234)));
+        assert_eq!(creds.groups, Some(vec![Gid::from_raw(1235)]));
+        assert_eq!(creds.umask, Some(Mode::from_bits_truncate(0o027)));
+
+        for bad in ["user = \"no-such-user-m7d\"", "group = \"no-such-group-m7d\"", "umask = \"999\""] {
+            let err = Credentials::resolve(&config(bad)).unwrap_err();
+            assert!(matches!(err, DaemonError::Config(_)), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_as_user() {
+        // 只有 root 才能切换用户
+        let nobody = match User::from_name("nobody") {
+            Ok(Some(user)) if Uid::effective().is_root() => user,
+            _ => return,
+        };
+        let cfg = config("capture_output = false\nuser = \"nobody\"\numask = \"077\"");
+        let cfg = ProcessConfig {
+            command: "/bin/sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("test $(id -u) = {} && test $(umask) = 0077 && test \"$USER\" = nobody", nobody.uid),
+            ],
+            ..cfg
+        };
+
+        let mut pm = ProcessManager::new();
+        let mut events = pm.take_events().unwrap();
+        pm.spawn(&cfg).await.unwrap();
+        let Some(ProcessEvent::Exited { exit, .. }) = events.recv().await else {
+            panic!("expected exit event");
+        };
+        assert!(exit.success(), "{}", exit);
+    }
+}
@@ -1,11 +1,17 @@
-use crate::config::{ProcessConfig, Schedule as ProcessSchedule, ScheduleType};
+use crate::config::{ProcessConfig, Schedule as ProcessSchedule};
 use crate::error::DaemonError;
+use crate::limits::{CgroupManager, CgroupUsage, DEFAULT_CGROUP_PARENT};
+use crate::process_output::LogRotation;
+use crate::procfs;
+use crate::storage::JobRun;
+use chrono::{DateTime, DurationRound, SubsecRound, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::process::Child;
+use tokio::sync::{broadcast, mpsc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessEntry {
@@ -13,6 +19,12 @@ pub struct ProcessEntry {
     pub pid: u32,
     pub start_time: u64,
     pub config: ProcessConfig,
+    /// 实例序号，从 0 开始
+    #[serde(default)]
+    pub instance: usize,
+    /// 是否由本守护进程的 watcher 任务等待退出（从状态文件恢复的进程为 false）
+    #[serde(skip)]
+    pub watched: bool,
 }
 
 impl ProcessEntry {
@@ -21,7 +33,16 @@ impl ProcessEntry {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        Self { name, pid, start_time, config }
+        Self { name, pid, start_time, config, instance: 0, watched: false }
+    }
+    
+    /// Name used in logs and alerts, "name:idx" for multi-instance processes
+    pub fn label(&self) -> String {
+        if self.config.instances > 1 {
+            format!("{}:{}", self.name, self.instance)
+        } else {
+            self.name.clone()
+        }
     }
     
     pub fn uptime(&self) -> u64 {
@@ -30,19 +51,46 @@ impl ProcessEntry {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessStatus {
     pub name: String,
     pub pid: u32,
     pub state: ProcessState,
     pub uptime: u64,
     pub memory: Option<u64>,
+    /// 整个进程组（含 Swoole 等子进程）的 RSS 之和
+    #[serde(default)]
+    pub group_memory: Option<u64>,
+    /// CPU 占用百分比，由守护进程按采样差值计算
+    #[serde(default)]
+    pub cpu: Option<f64>,
+    #[serde(default)]
+    pub threads: Option<u64>,
+    /// 打开的文件描述符数量
+    #[serde(default)]
+    pub fds: Option<u64>,
+    /// 守护进程启动以来的重启次数
+    #[serde(default)]
+    pub restarts: u32,
+    /// 调度进程的下次执行时间
+    #[serde(default)]
+    pub next_run: Option<DateTime<Utc>>,
+    /// cgroup 用量，仅在进程配置了 cgroup 限制时存在
+    #[serde(default)]
+    pub cgroup: Option<CgroupUsage>,
+    /// 实例序号，仅在配置了多个实例时存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<usize>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Schedule {
-    pub schedule_type: ScheduleType,
-    pub interval: Option<u64>,
-    pub expression: Option<String>,
+impl ProcessStatus {
+    /// Row key shown by `m7d status`, "name:idx" for multi-instance processes
+    pub fn label(&self) -> String {
+        match self.instance {
+            Some(instance) => format!("{}:{}", self.name, instance),
+            None => self.name.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -56,37 +104,184 @@ pub struct Scheduler {
     pub interval: Option<u64>,
     pub cron_expression: Option<String>,
     pub next_run: Option<Instant>,
+    /// 执行次数与耗时统计，导出为 Prometheus 指标
+    pub stats: JobStats,
     compiled_schedule: Option<cron::Schedule>,
+    /// cron 表达式按该时区计算，夏令时切换由 chrono-tz 处理
+    timezone: chrono_tz::Tz,
+    /// 最近一次触发对应的计划执行时间
+    pub last_slot: Option<DateTime<Utc>>,
+    /// 补跑时 `run_now` 记录的错过的时间点
+    catch_up_slot: Option<DateTime<Utc>>,
+    /// 展开 `H` 之后的 cron 表达式，表达式中没有 `H` 时为空
+    pub resolved_expression: Option<String>,
+    /// 每次执行的最大随机延迟（秒）
+    jitter: u64,
+    /// 本次待执行的随机延迟，`next_run` 已包含该延迟
+    next_delay: Duration,
+}
+
+/// 调度任务预览，`m7d schedule list` 的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleInfo {
+    pub name: String,
+    pub schedule: ProcessSchedule,
+    /// 展开 `H` 之后的 cron 表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_expression: Option<String>,
+    /// 接下来的执行时间（不含随机延迟）
+    pub upcoming: Vec<DateTime<Utc>>,
+    /// 下一次执行抽到的随机延迟（秒），未配置 jitter 时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_delay: Option<u64>,
+    /// 最近一次执行，历史数据库不可用时为空
+    pub last_run: Option<JobRun>,
+}
+
+/// 调度任务的执行统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobStats {
+    /// 已启动的次数
+    pub runs: u64,
+    /// 已结束的次数
+    pub completed: u64,
+    /// 以非 0 状态结束的次数
+    pub failures: u64,
+    /// 已结束执行的累计耗时
+    pub duration_sum: Duration,
+    pub last_duration: Option<Duration>,
+    pub last_exit_code: Option<i32>,
+}
+
+impl JobStats {
+    /// Record a finished run
+    pub fn record_completion(&mut self, duration: Duration, exit_code: Option<i32>) {
+        self.completed += 1;
+        if exit_code != Some(0) {
+            self.failures += 1;
+        }
+        self.duration_sum += duration;
+        self.last_duration = Some(duration);
+        self.last_exit_code = exit_code;
+    }
 }
 
 impl Scheduler {
-    pub fn from_config(schedule: &ProcessSchedule, global_interval: u64) -> Self {
+    /// Build the scheduler of process `name`; `H` tokens are hashed from the host name and `name`
+    pub fn from_config(name: &str, schedule: &ProcessSchedule, global_interval: u64) -> Self {
+        let mut resolved_expression = None;
         let (scheduler_type, interval, cron_expression, compiled_schedule) = match schedule.schedule_type {
             crate::config::ScheduleType::Interval => {
                 let interval = schedule.interval.unwrap_or(global_interval);
                 (SchedulerType::Interval, Some(interval), None, None)
             }
             crate::config::ScheduleType::Cron => {
-                let expr = schedule.expression.as_deref().unwrap_or("* * * * *");
-                tracing::debug!(expr = expr, "Cron: parsing expression");
-                let compiled = cron::Schedule::from_str(expr);
+                // 表达式已由 validate_config 校验
+                let mut expr = schedule.expression.clone().unwrap_or_default();
+                if expr.split_whitespace().any(Self::is_hash_token) {
+                    let seed = format!("{}/{}", sysinfo::System::host_name().unwrap_or_default(), name);
+                    match Self::resolve_expression(&expr, &seed) {
+                        Ok(resolved) => {
+                            expr = resolved.clone();
+                            resolved_expression = Some(resolved);
+                        }
+                        Err(e) => tracing::warn!(expr = expr.as_str(), error = e.as_str(), "Cron: failed to expand H"),
+                    }
+                }
+                tracing::debug!(expr = expr.as_str(), "Cron: parsing expression");
+                let compiled = cron::Schedule::from_str(&expr);
                 match &compiled {
                     Ok(s) => tracing::debug!(schedule = ?s, "Cron: expression parsed OK"),
-                    Err(e) => tracing::warn!(expr = expr, error = ?e, "Cron: failed to parse expression"),
+                    Err(e) => tracing::warn!(expr = expr.as_str(), error = ?e, "Cron: failed to parse expression"),
                 }
                 (SchedulerType::Cron, None, schedule.expression.clone(), compiled.ok())
             }
         };
 
-        let next_run = Self::calculate_next_run(scheduler_type.clone(), interval, cron_expression.as_deref(), compiled_schedule.as_ref());
-
-        Self {
+        let mut scheduler = Self {
             scheduler_type,
             interval,
             cron_expression,
-            next_run,
+            next_run: None,
+            stats: JobStats::default(),
             compiled_schedule,
-        }
+            // 时区已由 validate_config 校验
+            timezone: schedule.tz().unwrap_or(chrono_tz::UTC),
+            last_slot: None,
+            catch_up_slot: None,
+            resolved_expression,
+            jitter: schedule.jitter.unwrap_or(0),
+            next_delay: Duration::ZERO,
+        };
+        scheduler.schedule_next();
+        scheduler
+    }
+
+    /// Expand Jenkins-style `H` tokens into values derived from `seed`
+    ///
+    /// Supports `H`, `H/step`, `H(a-b)` and `H(a-b)/step` in the seconds through
+    /// weekday fields. The same seed always gives the same expression.
+    pub fn resolve_expression(expr: &str, seed: &str) -> Result<String, String> {
+        // 秒 分 时 日 月 周；日只取 1-28，保证每个月都会执行
+        const RANGES: [(u64, u64); 6] = [(0, 59), (0, 59), (0, 23), (1, 28), (1, 12), (1, 7)];
+
+        let fields: Vec<String> = expr
+            .split_whitespace()
+            .enumerate()
+            .map(|(i, field)| {
+                if !Self::is_hash_token(field) {
+                    return Ok(field.to_string());
+                }
+                let invalid = || format!("invalid H token '{}'", field);
+                let &(min, max) = RANGES.get(i).ok_or_else(|| format!("H is not supported in field {}", i + 1))?;
+                let rest = field.strip_prefix('H').ok_or_else(invalid)?;
+                let (range, step) = match rest.split_once('/') {
+                    Some((range, step)) => (range, Some(step.parse::<u64>().map_err(|_| invalid())?)),
+                    None => (rest, None),
+                };
+                let (low, high) = match range {
+                    "" => (min, max),
+                    _ => {
+                        let inner = range.strip_prefix('(').and_then(|r| r.strip_suffix(')')).ok_or_else(invalid)?;
+                        let (low, high) = inner.split_once('-').ok_or_else(invalid)?;
+                        (low.parse::<u64>().map_err(|_| invalid())?, high.parse::<u64>().map_err(|_| invalid())?)
+                    }
+                };
+                if low > high || low < min || high > max || step == Some(0) {
+                    return Err(invalid());
+                }
+
+                let hash = fnv1a(format!("{}#{}", seed, i).as_bytes());
+                Ok(match step {
+                    Some(step) => format!("{}-{}/{}", low + hash % step.min(high - low + 1), high, step),
+                    None => (low + hash % (high - low + 1)).to_string(),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(fields.join(" "))
+    }
+
+    /// Whether a cron field is an `H` token; other fields, such as `THU`, go to cron unchanged
+    fn is_hash_token(field: &str) -> bool {
+        field == "H" || field.starts_with("H/") || field.starts_with("H(")
+    }
+
+    /// Compute the next run and draw its random delay
+    fn schedule_next(&mut self) {
+        self.next_delay = Duration::from_secs(fastrand::u64(0..=self.jitter));
+        self.next_run = Self::calculate_next_run(
+            self.scheduler_type.clone(),
+            self.interval,
+            self.cron_expression.as_deref(),
+            self.compiled_schedule.as_ref(),
+            self.timezone,
+        )
+        .map(|next| next + self.next_delay);
+    }
+
+    /// Random delay of the pending run, zero without `jitter`
+    pub fn next_delay(&self) -> Duration {
+        self.next_delay
     }
 
     fn calculate_next_run(
@@ -94,6 +289,7 @@ impl Scheduler {
         interval: Option<u64>,
         cron_expression: Option<&str>,
         compiled_schedule: Option<&cron::Schedule>,
+        timezone: chrono_tz::Tz,
     ) -> Option<Instant> {
         match scheduler_type {
             SchedulerType::Interval => {
@@ -107,10 +303,10 @@ impl Scheduler {
                 tracing::debug!(expr = cron_expression, "Cron: trying to calculate next run");
                 if let Some(schedule) = compiled_schedule {
                     tracing::debug!(schedule = ?schedule, "Cron: schedule compiled OK");
-                    if let Some(dt) = schedule.upcoming(chrono::Utc).next() {
+                    if let Some(dt) = schedule.upcoming(timezone).next() {
                         let now = chrono::Utc::now();
                         let duration = dt.signed_duration_since(now);
-                        let next = Instant::now() + Duration::from_secs(duration.num_seconds() as u64);
+                        let next = Instant::now() + duration.to_std().unwrap_or(Duration::ZERO);
                         tracing::debug!(next_run = ?dt, now = ?now, diff_sec = duration.num_seconds(), "Cron next_run calculated");
                         return Some(next);
                     } else {
@@ -130,12 +326,11 @@ impl Scheduler {
         if let Some(next) = self.next_run {
             tracing::debug!(scheduler_type = ?self.scheduler_type, now_ms = now.elapsed().as_millis(), next_ms = ?next.elapsed().as_millis(), "should_run check");
             if now >= next {
-                self.next_run = Self::calculate_next_run(
-                    self.scheduler_type.clone(),
-                    self.interval,
-                    self.cron_expression.as_deref(),
-                    self.compiled_schedule.as_ref(),
-                );
+                // Instant 换算回墙上时间并去掉随机延迟，补跑时使用错过的时间点
+                let late = (now - next) + self.next_delay;
+                let slot = (Utc::now() - chrono::Duration::from_std(late).unwrap_or_default()).trunc_subsecs(0);
+                self.last_slot = Some(self.catch_up_slot.take().unwrap_or(slot));
+                self.schedule_next();
                 tracing::info!(scheduler_type = ?self.scheduler_type, "should_run: TRUE");
                 return true;
             }
@@ -143,17 +338,163 @@ impl Scheduler {
         tracing::debug!(scheduler_type = ?self.scheduler_type, "should_run: false");
         false
     }
+
+    /// Latest run that should have happened after `last_run`, if it is no older than `deadline`
+    pub fn missed_run(&self, last_run: DateTime<Utc>, now: DateTime<Utc>, deadline: Duration) -> Option<DateTime<Utc>> {
+        let deadline = chrono::Duration::from_std(deadline).ok()?;
+        let slot = match self.scheduler_type {
+            SchedulerType::Interval => {
+                let interval = chrono::Duration::seconds(self.interval? as i64);
+                let periods = (now - last_run).num_seconds() / interval.num_seconds().max(1);
+                (periods >= 1).then(|| last_run + interval * periods as i32)?
+            }
+            SchedulerType::Cron => {
+                // 超出 deadline 的时间点不会补跑，只需从窗口起点开始找
+                let start = last_run.max(now - deadline).with_timezone(&self.timezone);
+                self.compiled_schedule
+                    .as_ref()?
+                    .after(&start)
+                    .take_while(|t| *t <= now)
+                    .last()?
+                    .with_timezone(&Utc)
+            }
+        };
+        (now - slot <= deadline).then_some(slot)
+    }
+
+    /// Wall-clock time of the next run
+    pub fn next_run_time(&self) -> Option<DateTime<Utc>> {
+        let wait = self.next_run?.saturating_duration_since(Instant::now());
+        // 调度器以 Instant 计时，换算回墙上时间时取整到秒
+        (Utc::now() + chrono::Duration::from_std(wait).ok()?).duration_round(chrono::Duration::seconds(1)).ok()
+    }
+
+    /// The next `count` scheduled times, starting from the pending run
+    ///
+    /// The times exclude the random `jitter` delay; see `next_delay` for the pending run.
+    pub fn upcoming(&self, count: usize) -> Vec<DateTime<Utc>> {
+        let first = match (self.next_run_time(), chrono::Duration::from_std(self.next_delay)) {
+            (Some(next), Ok(delay)) => next - delay,
+            _ => return Vec::new(),
+        };
+        let rest: Vec<DateTime<Utc>> = match self.scheduler_type {
+            SchedulerType::Interval => {
+                let interval = chrono::Duration::seconds(self.interval.unwrap_or(0) as i64);
+                (1..count as i32).map(|i| first + interval * i).collect()
+            }
+            SchedulerType::Cron => match self.compiled_schedule {
+                Some(ref schedule) => schedule
+                    .after(&first.with_timezone(&self.timezone))
+                    .take(count.saturating_sub(1))
+                    .map(|t| t.with_timezone(&Utc))
+                    .collect(),
+                None => Vec::new(),
+            },
+        };
+        std::iter::once(first).chain(rest).take(count).collect()
+    }
+
+    /// Run at the next tick instead of waiting for the next slot
+    ///
+    /// `slot` is the missed run being caught up and is reported as its scheduled time.
+    pub fn run_now(&mut self, slot: DateTime<Utc>) {
+        self.next_run = Some(Instant::now());
+        self.next_delay = Duration::ZERO;
+        self.catch_up_slot = Some(slot);
+    }
+}
+
+/// 64 位 FNV-1a，`H` 的取值需要跨版本稳定，不能使用 std 的 DefaultHasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum ProcessState { Running, Stopped, Dead, Unknown }
+pub enum ProcessState { Running, Stopped, Dead, Unknown, Backoff, Fatal, Unhealthy, Waiting }
+
+/// 子进程退出信息：退出码或终止信号
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExitInfo {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+impl ExitInfo {
+    fn from_status(status: std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            Self { code: status.code(), signal: status.signal() }
+        }
+        #[cfg(not(unix))]
+        {
+            Self { code: status.code(), signal: None }
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl fmt::Display for ExitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {}", code),
+            (None, Some(signal)) => write!(f, "signal {}", signal),
+            (None, None) => write!(f, "unknown exit status"),
+        }
+    }
+}
+
+/// 子进程生命周期事件，由 watcher 任务发送给守护进程主循环
+#[derive(Debug, Clone)]
+pub enum ProcessEvent {
+    Exited { name: String, pid: u32, exit: ExitInfo },
+    /// 健康检查完成
+    HealthChecked { name: String, pid: u32, result: Result<(), String> },
+}
+
 pub struct ProcessManager {
     registry: HashMap<String, Vec<ProcessEntry>>,
+    events_tx: mpsc::UnboundedSender<ProcessEvent>,
+    events_rx: Option<mpsc::UnboundedReceiver<ProcessEvent>>,
+    log_reopen: broadcast::Sender<()>,
+    cgroups: CgroupManager,
 }
 
 impl ProcessManager {
     pub fn new() -> Self {
-        Self { registry: HashMap::new() }
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let (log_reopen, _) = broadcast::channel(4);
+        Self {
+            registry: HashMap::new(),
+            events_tx,
+            events_rx: Some(events_rx),
+            log_reopen,
+            cgroups: CgroupManager::new(DEFAULT_CGROUP_PARENT),
+        }
+    }
+
+    /// Ask all output writers to reopen their log files
+    pub fn reopen_logs(&self) {
+        let writers = self.log_reopen.send(()).unwrap_or(0);
+        tracing::info!(writers = writers, "Reopening process log files");
+    }
+
+    /// Take the receiver for child exit events (can only be taken once)
+    pub fn take_events(&mut self) -> Option<mpsc::UnboundedReceiver<ProcessEvent>> {
+        self.events_rx.take()
+    }
+
+    /// Use a different parent directory for per-process cgroups
+    pub fn set_cgroup_parent(&mut self, parent: impl Into<PathBuf>) {
+        self.cgroups = CgroupManager::new(parent);
+    }
+
+    /// Sender for events produced outside the manager (health probes)
+    pub fn event_sender(&self) -> mpsc::UnboundedSender<ProcessEvent> {
+        self.events_tx.clone()
     }
 
     /// Load state from file and verify processes are still alive
@@ -205,7 +546,15 @@ impl ProcessManager {
         Ok(())
     }
 
+    /// Spawn the lowest instance index that is not running
     pub async fn spawn(&mut self, config: &ProcessConfig) -> Result<u32, DaemonError> {
+        let instance = (0..)
+            .find(|i| !self.entries(&config.name).iter().any(|e| e.instance == *i))
+            .unwrap_or(0);
+        self.spawn_instance(config, instance).await
+    }
+
+    pub async fn spawn_instance(&mut self, config: &ProcessConfig, instance: usize) -> Result<u32, DaemonError> {
         if let Some(max) = config.max_instances {
             if let Some(entries) = self.registry.get(&config.name) {
                 if entries.len() >= max {
@@ -217,6 +566,7 @@ impl ProcessManager {
             }
         }
 
+        let config = &config.for_instance(instance);
         let mut cmd = tokio::process::Command::new(&config.command);
         cmd.args(&config.args);
         if let Some(ref wd) = config.working_directory {
@@ -226,6 +576,44 @@ impl ProcessManager {
             cmd.env(k, v);
         }
         
+        // 🔧 资源限制：rlimit 和加入 cgroup 需要在降权之前完成
+        #[cfg(unix)]
+        if let Some(ref limits) = config.limits {
+            let rlimits = limits.clone();
+            // SAFETY: apply_rlimits 只调用 setrlimit
+            unsafe {
+                cmd.pre_exec(move || crate::limits::apply_rlimits(&rlimits));
+            }
+            if limits.has_cgroup_limits() {
+                if let Some(procs) = self.cgroups.prepare(&config.name, limits) {
+                    // SAFETY: 只对已打开的文件做一次 write 系统调用
+                    unsafe {
+                        cmd.pre_exec(move || {
+                            use std::io::Write;
+                            (&procs).write_all(b"0")
+                        });
+                    }
+                }
+            }
+        }
+        
+        // 🔧 降权：在 exec 之前切换用户/用户组并设置 umask
+        #[cfg(unix)]
+        if let Some(creds) = crate::privileges::Credentials::resolve(config).map_err(|e| DaemonError::StartFailed {
+            name: config.name.clone(),
+            reason: e.to_string(),
+        })? {
+            for (k, v) in creds.environment() {
+                if !config.environment.contains_key(k) {
+                    cmd.env(k, v);
+                }
+            }
+            // SAFETY: apply 只调用 setgroups/setgid/setuid/umask，均为 async-signal-safe
+            unsafe {
+                cmd.pre_exec(move || creds.apply());
+            }
+        }
+        
         // 🔧 设置进程组：让子进程成为新进程组的 leader (PGID = PID)
         // 这样 kill(-(pid as i32)) 就能杀死整个进程组（包括所有 Swoole 子进程）
         cmd.process_group(0);
@@ -243,90 +631,210 @@ impl ProcessManager {
         
         // 🔧 启动输出捕获任务
         if config.capture_output {
-            if let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) {
+            if let (Some(stdout), Some(stderr), Some(log_file)) = (child.stdout.take(), child.stderr.take(), config.log_path()) {
                 let name = config.name.clone();
-                let log_file = config.log_file.clone().unwrap_or_else(|| {
-                    format!("/var/log/mi7soft-{}.log", config.name)
-                });
-                let max_size = config.max_log_size;
+                let rotation = LogRotation::from_config(config);
+                let reopen = self.log_reopen.subscribe();
                 
                 tokio::spawn(async move {
                     use crate::process_output::OutputCapture;
-                    let (mut capture, mut receiver) = OutputCapture::new(
+                    let (capture, receiver) = OutputCapture::new(
                         name,
                         std::path::PathBuf::from(&log_file),
-                        max_size,
+                        rotation,
                     );
                     capture.capture_stdout(stdout);
                     capture.capture_stderr(stderr);
-                    capture.start_writer(receiver);
+                    capture.start_writer(receiver, reopen);
                 });
             }
         }
         
-        let pid = child.id().unwrap() as u32;
+        let pid = child.id().unwrap();
+        
+        // 🔧 由 watcher 任务持有 Child 并等待其退出，及时回收僵尸进程并上报退出状态
+        let events_tx = self.events_tx.clone();
+        let name = config.name.clone();
+        tokio::spawn(async move {
+            let exit = match child.wait().await {
+                Ok(status) => ExitInfo::from_status(status),
+                Err(e) => {
+                    tracing::error!(process = name.as_str(), pid = pid, error = %e, "Failed to wait for child");
+                    ExitInfo { code: None, signal: None }
+                }
+            };
+            let _ = events_tx.send(ProcessEvent::Exited { name, pid, exit });
+        });
+        
+        let mut entry = ProcessEntry::new(config.name.clone(), pid, config.clone());
+        entry.instance = instance;
+        entry.watched = true;
         self.registry.entry(config.name.clone())
-            .or_insert_with(Vec::new)
-            .push(ProcessEntry::new(config.name.clone(), pid, config.clone()));
+            .or_default()
+            .push(entry);
         Ok(pid)
     }
+
+    /// Remove an exited child from the registry
+    ///
+    /// Returns `None` if the entry was already removed, e.g. because the
+    /// process was stopped on purpose.
+    pub fn remove_exited(&mut self, name: &str, pid: u32) -> Option<ProcessEntry> {
+        let entries = self.registry.get_mut(name)?;
+        let index = entries.iter().position(|e| e.pid == pid)?;
+        let entry = entries.remove(index);
+        if entries.is_empty() {
+            self.registry.remove(name);
+            self.cgroups.remove(name);
+        }
+        Some(entry)
+    }
     pub async fn stop(&mut self, name: &str) -> Result<Vec<u32>, DaemonError> {
         if let Some(entries) = self.registry.get(name) {
             if entries.is_empty() {
                 return Err(DaemonError::NotRunning { name: name.to_string() });
             }
-            let pids: Vec<u32> = entries.iter().map(|e| e.pid).collect();
-            let mut stopped = Vec::new();
-            for pid in pids {
-                if let Ok(p) = self.stop_by_pid(pid).await { stopped.push(p); }
-            }
+            let targets: Vec<(u32, ProcessConfig)> = entries.iter().map(|e| (e.pid, e.config.clone())).collect();
+            let stopped = self.terminate(&targets).await;
             self.registry.remove(name);
+            self.cgroups.remove(name);
             Ok(stopped)
         } else {
             Err(DaemonError::NotRunning { name: name.to_string() })
         }
     }
 
-    pub async fn stop_by_pid(&self, pid: u32) -> Result<u32, DaemonError> {
+    /// Stop a single instance, leaving the other instances running
+    pub async fn stop_instance(&mut self, name: &str, instance: usize) -> Result<u32, DaemonError> {
+        let entry = self.entries(name)
+            .iter()
+            .find(|e| e.instance == instance)
+            .ok_or_else(|| DaemonError::NotRunning { name: format!("{}:{}", name, instance) })?;
+        let (pid, config) = (entry.pid, entry.config.clone());
+        let stopped = self.stop_by_pid(pid, &config).await;
+        self.remove_exited(name, pid);
+        stopped
+    }
+
+    pub async fn stop_by_pid(&self, pid: u32, config: &ProcessConfig) -> Result<u32, DaemonError> {
+        self.terminate(&[(pid, config.clone())])
+            .await
+            .pop()
+            .ok_or_else(|| DaemonError::StopFailed {
+                name: format!("PID {}", pid),
+                reason: "SIGKILL failed".to_string(),
+            })
+    }
+
+    /// Send each process its `stop_signal`, wait up to `stop_timeout` and SIGKILL the rest
+    ///
+    /// All processes are signalled first and waited for together, so stopping
+    /// several instances takes one timeout rather than one per instance.
+    /// Returns the PIDs that are gone.
+    async fn terminate(&self, targets: &[(u32, ProcessConfig)]) -> Vec<u32> {
         #[cfg(unix)]
         {
             use nix::sys::signal::{self, Signal};
-            use nix::unistd::Pid;
             use std::time::Duration;
             
-            // 🔧 杀死整个进程组：使用负数 PID 表示 PGID
-            // 这样会同时杀死 Master + Worker + TaskWorker + Manager 所有进程
-            let pgid = Pid::from_raw(-(pid as i32));
-            let _ = signal::kill(pgid, Signal::SIGTERM);
+            for (pid, config) in targets {
+                let sig = crate::signal::parse(&config.stop_signal).unwrap_or(Signal::SIGTERM);
+                let _ = signal::kill(signal_target(*pid, config.kill_group), sig);
+            }
             
-            for _ in 0..50 {
+            let started = Instant::now();
+            let mut stopped = Vec::new();
+            let mut remaining: Vec<&(u32, ProcessConfig)> = targets.iter().collect();
+            while !remaining.is_empty() {
                 tokio::time::sleep(Duration::from_millis(100)).await;
-                if !self.is_process_alive(pid) { return Ok(pid); }
+                let elapsed = started.elapsed();
+                remaining.retain(|(pid, config)| {
+                    if !self.is_process_alive(*pid) {
+                        stopped.push(*pid);
+                        return false;
+                    }
+                    if elapsed < Duration::from_secs(config.stop_timeout) {
+                        return true;
+                    }
+                    // 超时仍未退出：强制杀死（kill_group 时为整个进程组）
+                    match signal::kill(signal_target(*pid, config.kill_group), Signal::SIGKILL) {
+                        Ok(()) => stopped.push(*pid),
+                        Err(e) => tracing::error!(pid = pid, error = %e, "SIGKILL failed"),
+                    }
+                    false
+                });
             }
-            
-            // 强制杀死整个进程组
-            signal::kill(pgid, Signal::SIGKILL)
-                .map_err(|e| DaemonError::StopFailed {
-                    name: format!("PID {}", pid),
-                    reason: format!("SIGKILL failed: {}", e),
-                })?;
             tokio::time::sleep(Duration::from_millis(100)).await;
-            Ok(pid)
+            stopped
         }
         #[cfg(not(unix))]
         {
-            Err(DaemonError::StopFailed { name: format!("PID {}", pid), reason: "Not supported".to_string() })
+            let _ = targets;
+            Vec::new()
+        }
+    }
+
+    /// Send a signal to the running instances of a process without stopping them
+    ///
+    /// With `instance` only that instance is signalled. Returns the signalled PIDs.
+    #[cfg(unix)]
+    pub fn signal(&self, name: &str, instance: Option<usize>, sig: nix::sys::signal::Signal) -> Result<Vec<u32>, DaemonError> {
+        let entries: Vec<&ProcessEntry> = self.entries(name)
+            .iter()
+            .filter(|e| instance.is_none_or(|i| e.instance == i))
+            .collect();
+        if entries.is_empty() {
+            let name = match instance {
+                Some(i) => format!("{}:{}", name, i),
+                None => name.to_string(),
+            };
+            return Err(DaemonError::NotRunning { name });
         }
+        entries
+            .into_iter()
+            .map(|e| {
+                nix::sys::signal::kill(signal_target(e.pid, e.config.kill_group), sig)
+                    .map(|_| e.pid)
+                    .map_err(|err| DaemonError::Signal(format!("Failed to send {} to PID {}: {}", sig, e.pid, err)))
+            })
+            .collect()
     }
+
+    /// Apply settings that take effect without a restart (stop/reload signals) to running entries
+    pub fn update_config(&mut self, config: &ProcessConfig) {
+        if let Some(entries) = self.registry.get_mut(&config.name) {
+            for entry in entries {
+                entry.config = config.for_instance(entry.instance);
+            }
+        }
+    }
+
     pub async fn restart(&mut self, config: &ProcessConfig) -> Result<Vec<u32>, DaemonError> {
         self.stop(&config.name).await.ok();
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        let pid = self.spawn(config).await?;
-        Ok(vec![pid])
+        let mut pids = Vec::with_capacity(config.instances);
+        for instance in 0..config.instances {
+            pids.push(self.spawn_instance(config, instance).await?);
+        }
+        Ok(pids)
+    }
+
+    /// Restart a single instance, leaving the other instances running
+    pub async fn restart_instance(&mut self, config: &ProcessConfig, instance: usize) -> Result<u32, DaemonError> {
+        self.stop_instance(&config.name, instance).await.ok();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        self.spawn_instance(config, instance).await
     }
 
     pub fn status(&self, name: &str) -> Result<Vec<ProcessStatus>, DaemonError> {
+        self.status_with(name, &procfs::ProcessGroups::scan())
+    }
+
+    /// Status of one process, reusing a `/proc` scan shared by the whole query
+    fn status_with(&self, name: &str, groups: &procfs::ProcessGroups) -> Result<Vec<ProcessStatus>, DaemonError> {
         if let Some(entries) = self.registry.get(name) {
+            let mut entries: Vec<&ProcessEntry> = entries.iter().collect();
+            entries.sort_by_key(|e| e.instance);
             let mut statuses = Vec::new();
             for entry in entries {
                 let state = if self.is_process_alive(entry.pid) { ProcessState::Running } else { ProcessState::Dead };
@@ -336,6 +844,15 @@ impl ProcessManager {
                     state,
                     uptime: entry.uptime(),
                     memory: self.get_process_memory(entry.pid),
+                    // 进程以 process_group(0) 启动，PGID 等于 PID
+                    group_memory: groups.group_rss(entry.pid),
+                    cpu: None,
+                    threads: procfs::read_stat(entry.pid).map(|s| s.threads),
+                    fds: procfs::fd_count(entry.pid),
+                    restarts: 0,
+                    next_run: None,
+                    cgroup: self.cgroups.usage(name),
+                    instance: (entry.config.instances > 1).then_some(entry.instance),
                 });
             }
             Ok(statuses)
@@ -345,30 +862,36 @@ impl ProcessManager {
     }
 
     pub fn status_all(&self) -> Vec<ProcessStatus> {
+        let groups = procfs::ProcessGroups::scan();
         let mut all = Vec::new();
         for name in self.registry.keys() {
-            if let Ok(s) = self.status(name) { all.extend(s); }
+            if let Ok(s) = self.status_with(name, &groups) { all.extend(s); }
         }
         all
     }
-    pub fn cleanup_dead(&mut self) -> Vec<String> {
-        let mut dead_names = Vec::new();
+    /// Remove dead processes that have no watcher task
+    ///
+    /// Children spawned by this daemon are reaped by their watcher and reported
+    /// through `ProcessEvent`; only processes adopted from the state file are
+    /// detected by polling here.
+    pub fn cleanup_dead(&mut self) -> Vec<ProcessEntry> {
         let mut to_remove = Vec::new();
         for (name, entries) in &self.registry {
             for entry in entries {
-                if !self.is_process_alive(entry.pid) {
+                if !entry.watched && !self.is_process_alive(entry.pid) {
                     to_remove.push((name.clone(), entry.pid));
-                    dead_names.push(name.clone());
                 }
             }
         }
-        for (name, pid) in to_remove {
-            if let Some(entries) = self.registry.get_mut(&name) {
-                entries.retain(|e| e.pid != pid);
-                if entries.is_empty() { self.registry.remove(&name); }
-            }
-        }
-        dead_names
+        to_remove
+            .into_iter()
+            .filter_map(|(name, pid)| self.remove_exited(&name, pid))
+            .collect()
+    }
+
+    /// Registry entries for a process name
+    pub fn entries(&self, name: &str) -> &[ProcessEntry] {
+        self.registry.get(name).map(|e| e.as_slice()).unwrap_or(&[])
     }
 
     pub fn process_names(&self) -> Vec<String> {
@@ -383,22 +906,20 @@ impl ProcessManager {
             if let Ok(content) = std::fs::read_to_string(&stat_path) {
                 // Format: pid (name) state ...
                 // State is the 3rd field, e.g., "R", "S", "Z", "X", etc.
-                if let Some(state_start) = content.find('(') {
-                    if let Some(state_end) = content.find(')') {
-                        if state_end + 2 < content.len() {
-                            let state = content.chars().nth(state_end + 2).unwrap_or('X');
-                            tracing::info!("PID {} state: '{}'", pid, state);
-                            // 'Z' = zombie, 'X' = dead
-                            if state == 'Z' || state == 'X' {
-                                return false;
-                            }
+                if let Some(state_end) = content.rfind(')') {
+                    if state_end + 2 < content.len() {
+                        let state = content.chars().nth(state_end + 2).unwrap_or('X');
+                        tracing::debug!("PID {} state: '{}'", pid, state);
+                        // 'Z' = zombie, 'X' = dead
+                        if state == 'Z' || state == 'X' {
+                            return false;
                         }
                     }
                 }
             }
             
             // Fallback: use kill(pid, 0)
-            use nix::sys::signal::{kill, Signal};
+            use nix::sys::signal::kill;
             use nix::unistd::Pid;
             kill(Pid::from_raw(pid as i32), None).is_ok()
         }
@@ -406,25 +927,23 @@ impl ProcessManager {
         { false }
     }
 
-    fn get_process_memory(&self, pid: u32) -> Option<u64> {
-        #[cfg(unix)]
-        {
-            let path = format!("/proc/{}/statm", pid);
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                if let Some(rss_str) = content.split_whitespace().nth(1) {
-                    if let Ok(rss) = rss_str.parse::<u64>() { return Some(rss * 4096); }
-                }
-            }
-            None
-        }
-        #[cfg(not(unix))]
-        { None }
+    pub fn get_process_memory(&self, pid: u32) -> Option<u64> {
+        procfs::read_rss(pid)
     }
 }
 impl Default for ProcessManager {
     fn default() -> Self { Self::new() }
 }
 
+/// Signal target: the process group (PGID = PID, negative) or only the process
+#[cfg(unix)]
+fn signal_target(pid: u32, group: bool) -> nix::unistd::Pid {
+    // 🔧 进程以 process_group(0) 启动，负数 PID 表示整个进程组
+    // 这样会同时通知 Master + Worker + TaskWorker + Manager 所有进程
+    let pid = pid as i32;
+    nix::unistd::Pid::from_raw(if group { -pid } else { pid })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -437,18 +956,271 @@ mod tests {
     
     #[test]
     fn test_process_entry() {
-        let cfg = ProcessConfig {
-            name: "test".to_string(),
-            command: "/bin/sleep".to_string(),
-            args: vec![],
-            working_directory: None,
-            environment: std::collections::HashMap::new(),
-            auto_restart: false,
-            log_file: None,
-            max_instances: None,
-        };
+        let cfg: ProcessConfig = toml::from_str(
+            r#"
+name = "test"
+command = "/bin/sleep"
+"#,
+        )
+        .unwrap();
         let e = ProcessEntry::new("test".to_string(), 1234, cfg);
         assert_eq!(e.name, "test");
         assert_eq!(e.pid, 1234);
     }
+
+    #[test]
+    fn test_upcoming() {
+        let schedule = |content: &str| Scheduler::from_config("job", &toml::from_str::<ProcessSchedule>(content).unwrap(), 5);
+
+        let shanghai = schedule("type = \"cron\"\nexpression = \"0 0 3 * * *\"\ntimezone = \"Asia/Shanghai\"");
+        let runs = shanghai.upcoming(3);
+        assert_eq!(runs.len(), 3);
+        assert!(runs.iter().all(|t| t.format("%H:%M:%S").to_string() == "19:00:00"));
+        assert!(runs.windows(2).all(|w| w[1] - w[0] == chrono::Duration::days(1)));
+
+        let interval = schedule("type = \"interval\"\ninterval = 60");
+        let runs = interval.upcoming(4);
+        assert_eq!(runs.len(), 4);
+        assert!(runs.windows(2).all(|w| w[1] - w[0] == chrono::Duration::seconds(60)));
+        assert!(interval.upcoming(0).is_empty());
+    }
+
+    #[test]
+    fn test_hash_tokens() {
+        let resolve = Scheduler::resolve_expression;
+        assert_eq!(resolve("0 0 3 * * *", "web").unwrap(), "0 0 3 * * *");
+
+        let expr = resolve("H H 3 * * *", "host-a/report").unwrap();
+        assert_eq!(expr, resolve("H H 3 * * *", "host-a/report").unwrap());
+        let fields: Vec<u64> = expr.split(' ').take(2).map(|f| f.parse().unwrap()).collect();
+        assert!(fields.iter().all(|f| *f < 60));
+        assert!(expr.ends_with(" 3 * * *"));
+
+        // 不同主机得到不同的分钟
+        let minutes: std::collections::HashSet<String> = (0..20)
+            .map(|i| resolve("0 H 3 * * *", &format!("host-{}/report", i)).unwrap())
+            .collect();
+        assert!(minutes.len() > 1);
+
+        for seed in ["a", "b", "c", "d"] {
+            let expr = resolve("0 H(10-19) H/6 * * *", seed).unwrap();
+            let fields: Vec<&str> = expr.split(' ').collect();
+            assert!((10..=19).contains(&fields[1].parse::<u64>().unwrap()), "{}", expr);
+            let (start, rest) = fields[2].split_once('-').unwrap();
+            assert!(start.parse::<u64>().unwrap() < 6 && rest == "23/6", "{}", expr);
+            cron::Schedule::from_str(&expr).unwrap();
+        }
+
+        for invalid in ["0 H(30-20) * * * *", "0 H(0-70) * * * *", "0 H/0 * * * *", "0 H(1-5 * * * *", "0 0 0 * * * H"] {
+            assert!(resolve(invalid, "seed").is_err(), "{}", invalid);
+        }
+
+        // 星期、月份名称中的 H 不是哈希记号，原样交给 cron
+        for expr in ["0 0 3 * * THU", "0 0 3 * * THU-SAT", "0 0 3 * MAR,AUG Thu", "0 xH * * * *"] {
+            assert_eq!(resolve(expr, "seed").unwrap(), expr);
+        }
+        let schedule = toml::from_str::<ProcessSchedule>("type = \"cron\"\nexpression = \"0 0 3 * * THU-SAT\"").unwrap();
+        let scheduler = Scheduler::from_config("report", &schedule, 5);
+        assert!(scheduler.resolved_expression.is_none());
+        assert!(scheduler.compiled_schedule.is_some());
+    }
+
+    #[test]
+    fn test_jitter() {
+        let schedule = toml::from_str::<ProcessSchedule>("type = \"interval\"\ninterval = 60\njitter = 30").unwrap();
+        for _ in 0..20 {
+            let scheduler = Scheduler::from_config("job", &schedule, 5);
+            let delay = scheduler.next_delay();
+            assert!(delay <= Duration::from_secs(30));
+            let wait = scheduler.next_run.unwrap() - Instant::now();
+            assert!(wait > delay + Duration::from_secs(59) && wait <= delay + Duration::from_secs(60));
+            // 预览的时间点不含随机延迟
+            let first = scheduler.upcoming(1)[0];
+            assert!((first - Utc::now() - chrono::Duration::seconds(60)).num_seconds().abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_missed_run() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let schedule = |content: &str| Scheduler::from_config("job", &toml::from_str::<ProcessSchedule>(content).unwrap(), 5);
+        let day = Duration::from_secs(86400);
+
+        // 北京时间每天 03:00 即 UTC 19:00
+        let shanghai = schedule("type = \"cron\"\nexpression = \"0 0 3 * * *\"\ntimezone = \"Asia/Shanghai\"");
+        assert_eq!(
+            shanghai.missed_run(at("2026-03-01T00:00:00Z"), at("2026-03-01T20:00:00Z"), day),
+            Some(at("2026-03-01T19:00:00Z"))
+        );
+        assert_eq!(shanghai.missed_run(at("2026-03-01T00:00:00Z"), at("2026-03-01T20:00:00Z"), Duration::from_secs(1800)), None);
+        assert_eq!(shanghai.missed_run(at("2026-03-01T19:00:00Z"), at("2026-03-01T20:00:00Z"), day), None);
+
+        // 纽约 3 月 8 日进入夏令时，09:00 从 UTC 14:00 变为 13:00
+        let new_york = schedule("type = \"cron\"\nexpression = \"0 0 9 * * *\"\ntimezone = \"America/New_York\"");
+        assert_eq!(
+            new_york.missed_run(at("2026-03-06T15:00:00Z"), at("2026-03-07T14:30:00Z"), day),
+            Some(at("2026-03-07T14:00:00Z"))
+        );
+        assert_eq!(
+            new_york.missed_run(at("2026-03-08T15:00:00Z"), at("2026-03-09T13:30:00Z"), day),
+            Some(at("2026-03-09T13:00:00Z"))
+        );
+
+        let interval = schedule("type = \"interval\"\ninterval = 60");
+        let last = at("2026-03-01T00:00:00Z");
+        let now = at("2026-03-01T00:02:30Z");
+        assert_eq!(interval.missed_run(last, now, Duration::from_secs(60)), Some(at("2026-03-01T00:02:00Z")));
+        assert_eq!(interval.missed_run(last, now, Duration::from_secs(10)), None);
+        assert_eq!(interval.missed_run(last, at("2026-03-01T00:00:59Z"), day), None);
+
+        // 补跑时报告错过的时间点，而不是实际触发时间
+        let mut catch_up = schedule("type = \"interval\"\ninterval = 3600");
+        catch_up.run_now(last);
+        assert!(catch_up.should_run());
+        assert_eq!(catch_up.last_slot, Some(last));
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_reported() {
+        let cfg: ProcessConfig = toml::from_str(
+            r#"
+name = "exiter"
+command = "/bin/sh"
+args = ["-c", "exit 3"]
+capture_output = false
+"#,
+        )
+        .unwrap();
+
+        let mut pm = ProcessManager::new();
+        let mut events = pm.take_events().unwrap();
+        let pid = pm.spawn(&cfg).await.unwrap();
+
+        let Some(ProcessEvent::Exited { name, pid: exited_pid, exit }) = events.recv().await else {
+            panic!("expected exit event");
+        };
+        assert_eq!(name, "exiter");
+        assert_eq!(exited_pid, pid);
+        assert_eq!(exit.code, Some(3));
+        assert!(!exit.success());
+
+        assert!(pm.remove_exited("exiter", pid).is_some());
+        assert!(pm.remove_exited("exiter", pid).is_none());
+        assert!(pm.process_names().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_signal_reported() {
+        let cfg: ProcessConfig = toml::from_str(
+            r#"
+name = "sleeper"
+command = "/bin/sleep"
+args = ["30"]
+capture_output = false
+"#,
+        )
+        .unwrap();
+
+        let mut pm = ProcessManager::new();
+        let mut events = pm.take_events().unwrap();
+        let pid = pm.spawn(&cfg).await.unwrap();
+
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+        kill(Pid::from_raw(pid as i32), Signal::SIGKILL).unwrap();
+
+        let Some(ProcessEvent::Exited { exit, .. }) = events.recv().await else {
+            panic!("expected exit event");
+        };
+        assert_eq!(exit.code, None);
+        assert_eq!(exit.signal, Some(9));
+        assert_eq!(exit.to_string(), "signal 9");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_instances() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cfg: ProcessConfig = toml::from_str(&format!(
+            r#"
+name = "worker"
+command = "/bin/sh"
+args = ["-c", "echo $M7D_INSTANCE > {dir}/env-{{instance}}; exec sleep 30"]
+log_file = "{dir}/worker-{{instance}}.log"
+instances = 3
+"#,
+            dir = dir.path().display()
+        ))
+        .unwrap();
+
+        let mut pm = ProcessManager::new();
+        for _ in 0..cfg.instances {
+            pm.spawn(&cfg).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        for i in 0..3 {
+            let env = std::fs::read_to_string(dir.path().join(format!("env-{}", i))).unwrap();
+            assert_eq!(env.trim(), i.to_string());
+            assert!(dir.path().join(format!("worker-{}.log", i)).exists());
+        }
+
+        // 只停止 1 号实例，其余实例不受影响
+        let pids: Vec<u32> = pm.entries("worker").iter().map(|e| e.pid).collect();
+        pm.stop_instance("worker", 1).await.unwrap();
+        let labels: Vec<String> = pm.status("worker").unwrap().iter().map(|s| s.label()).collect();
+        assert_eq!(labels, vec!["worker:0", "worker:2"]);
+        assert!(pm.stop_instance("worker", 1).await.is_err());
+
+        // 补齐时复用空出来的序号
+        let pid = pm.spawn(&cfg).await.unwrap();
+        assert_eq!(pm.entries("worker").iter().find(|e| e.pid == pid).unwrap().instance, 1);
+        assert!(pids.iter().filter(|p| **p != pids[1]).all(|p| pm.entries("worker").iter().any(|e| e.pid == *p)));
+
+        pm.stop("worker").await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_and_reload_signals() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cfg: ProcessConfig = toml::from_str(&format!(
+            r#"
+name = "graceful"
+command = "/bin/sh"
+args = ["-c", "trap 'echo reload >> {dir}/signals' USR1; trap 'echo quit >> {dir}/signals; exit 0' QUIT; trap '' TERM; while :; do sleep 0.1; done"]
+capture_output = false
+stop_signal = "SIGQUIT"
+reload_signal = "USR1"
+kill_group = false
+"#,
+            dir = dir.path().display()
+        ))
+        .unwrap();
+        let signals = || std::fs::read_to_string(dir.path().join("signals")).unwrap_or_default();
+
+        let mut pm = ProcessManager::new();
+        let pid = pm.spawn(&cfg).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // 重载信号不重启进程
+        let usr1 = crate::signal::parse(cfg.reload_signal.as_deref().unwrap()).unwrap();
+        assert_eq!(pm.signal("graceful", None, usr1).unwrap(), vec![pid]);
+        assert!(pm.signal("graceful", Some(1), usr1).is_err());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(signals(), "reload\n");
+        assert!(pm.is_process_alive(pid));
+
+        assert_eq!(pm.stop("graceful").await.unwrap(), vec![pid]);
+        assert_eq!(signals(), "reload\nquit\n");
+
+        // 忽略停止信号的进程在 stop_timeout 后被强制杀死
+        let stubborn = ProcessConfig { stop_signal: "SIGTERM".to_string(), stop_timeout: 1, kill_group: true, ..cfg };
+        let pid = pm.spawn(&stubborn).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let started = Instant::now();
+        assert_eq!(pm.stop("graceful").await.unwrap(), vec![pid]);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(!pm.is_process_alive(pid));
+    }
 }
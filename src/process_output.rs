@@ -2,11 +2,13 @@
 //! 
 //! 负责捕获进程的 stdout/stderr 并存储到文件
 
-use std::path::PathBuf;
+use crate::config::ProcessConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::fs::{File, OpenOptions};
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info};
 
 /// 日志行
 #[derive(Debug, Clone)]
@@ -27,7 +29,7 @@ pub enum LogStream {
 pub struct OutputCapture {
     process_name: String,
     log_file: PathBuf,
-    max_size: Option<u64>,
+    rotation: LogRotation,
     sender: mpsc::UnboundedSender<LogLine>,
 }
 
@@ -36,7 +38,7 @@ impl OutputCapture {
     pub fn new(
         process_name: String,
         log_file: PathBuf,
-        max_size: Option<u64>,
+        rotation: LogRotation,
     ) -> (Self, mpsc::UnboundedReceiver<LogLine>) {
         let (sender, receiver) = mpsc::unbounded_channel();
         
@@ -44,7 +46,7 @@ impl OutputCapture {
             Self {
                 process_name,
                 log_file,
-                max_size,
+                rotation,
                 sender,
             },
             receiver,
@@ -100,28 +102,31 @@ impl OutputCapture {
     }
     
     /// 启动日志写入器
-    pub fn start_writer(&self, mut receiver: mpsc::UnboundedReceiver<LogLine>) {
+    ///
+    /// `reopen` 收到通知时重新打开日志文件（SIGUSR1，配合外部 logrotate 使用）。
+    pub fn start_writer(
+        &self,
+        mut receiver: mpsc::UnboundedReceiver<LogLine>,
+        mut reopen: broadcast::Receiver<()>,
+    ) {
         let log_file = self.log_file.clone();
-        let max_size = self.max_size;
+        let rotation = self.rotation.clone();
         let process_name = self.process_name.clone();
         
         tokio::spawn(async move {
-            // 打开日志文件
-            let mut file = match OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&log_file)
-                .await
-            {
-                Ok(f) => f,
+            use tokio::io::AsyncWriteExt;
+            
+            // 打开日志文件；失败时仍然继续读取管道，避免子进程因管道写满而阻塞
+            let mut writer = match LogFileWriter::open(&log_file).await {
+                Ok(w) => w,
                 Err(e) => {
                     error!(
                         process = process_name.as_str(),
                         path = ?log_file,
                         error = %e,
-                        "Failed to open log file"
+                        "Failed to open log file, dropping output until it can be opened"
                     );
-                    return;
+                    LogFileWriter::closed()
                 }
             };
             
@@ -131,38 +136,76 @@ impl OutputCapture {
                 "Log writer started"
             );
             
-            while let Some(log_line) = receiver.recv().await {
-                // 格式化日志行
-                let formatted = format!(
-                    "[{}] [{}] {}\n",
-                    chrono::DateTime::from_timestamp(log_line.timestamp, 0)
-                        .unwrap()
-                        .format("%Y-%m-%d %H:%M:%S"),
-                    if log_line.stream == LogStream::Stdout { "OUT" } else { "ERR" },
-                    log_line.content
-                );
-                
-                // 写入文件
-                use tokio::io::AsyncWriteExt;
-                if let Err(e) = file.write_all(formatted.as_bytes()).await {
-                    error!(
-                        process = process_name.as_str(),
-                        error = %e,
-                        "Failed to write log"
-                    );
-                }
-                
-                // 检查文件大小（简化版本，实际应该使用日志轮转）
-                if let Some(max) = max_size {
-                    if let Ok(metadata) = file.metadata().await {
-                        if metadata.len() > max {
-                            warn!(
-                                process = process_name.as_str(),
-                                size = metadata.len(),
-                                max = max,
-                                "Log file size exceeded"
-                            );
-                            // TODO: 实现日志轮转
+            // 定时检查按时间轮转，并重试打开失败的日志文件，不依赖是否有新的输出
+            let mut ticker = tokio::time::interval(rotation.check_period());
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            
+            let mut reopen_open = true;
+            loop {
+                tokio::select! {
+                    line = receiver.recv() => {
+                        let log_line = match line {
+                            Some(l) => l,
+                            None => break,
+                        };
+                        
+                        // 格式化日志行
+                        let formatted = format!(
+                            "[{}] [{}] {}\n",
+                            chrono::DateTime::from_timestamp(log_line.timestamp, 0)
+                                .unwrap()
+                                .format("%Y-%m-%d %H:%M:%S"),
+                            if log_line.stream == LogStream::Stdout { "OUT" } else { "ERR" },
+                            log_line.content
+                        );
+                        
+                        // 写入文件，日志文件不可用时丢弃
+                        let file = match writer.file {
+                            Some(ref mut file) => file,
+                            None => continue,
+                        };
+                        match file.write_all(formatted.as_bytes()).await {
+                            Ok(()) => writer.written += formatted.len() as u64,
+                            Err(e) => {
+                                // 关闭文件，由定时器重新打开，避免按未写入的字节数反复轮转
+                                error!(
+                                    process = process_name.as_str(),
+                                    error = %e,
+                                    "Failed to write log"
+                                );
+                                writer.file = None;
+                                continue;
+                            }
+                        }
+                        
+                        if rotation.is_due(writer.written, writer.age()) {
+                            writer = rotate_and_reopen(&process_name, &log_file, &rotation, writer).await;
+                        }
+                    }
+                    _ = ticker.tick() => {
+                        if writer.file.is_none() {
+                            if let Ok(w) = LogFileWriter::open(&log_file).await {
+                                info!(process = process_name.as_str(), path = ?log_file, "Log file reopened");
+                                writer = w;
+                            }
+                        } else if rotation.is_due(writer.written, writer.age()) {
+                            writer = rotate_and_reopen(&process_name, &log_file, &rotation, writer).await;
+                        }
+                    }
+                    result = reopen.recv(), if reopen_open => {
+                        match result {
+                            Ok(()) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                                debug!(process = process_name.as_str(), "Reopening log file");
+                                match LogFileWriter::open(&log_file).await {
+                                    Ok(w) => writer = w,
+                                    Err(e) => error!(
+                                        process = process_name.as_str(),
+                                        error = %e,
+                                        "Failed to reopen log file"
+                                    ),
+                                }
+                            }
+                            Err(broadcast::error::RecvError::Closed) => reopen_open = false,
                         }
                     }
                 }
@@ -173,6 +216,172 @@ impl OutputCapture {
     }
 }
 
+/// 日志轮转配置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogRotation {
+    /// 超过该大小（字节）时轮转
+    pub max_size: Option<u64>,
+    /// 距离文件创建超过该时间时轮转
+    pub interval: Option<Duration>,
+    /// 保留的历史文件数量
+    pub max_files: usize,
+    /// 是否 gzip 压缩历史文件
+    pub compress: bool,
+}
+
+impl LogRotation {
+    pub fn from_config(config: &ProcessConfig) -> Self {
+        Self {
+            max_size: config.max_log_size,
+            interval: config.log_rotate_interval.map(Duration::from_secs),
+            max_files: config.max_log_files,
+            compress: config.compress_logs,
+        }
+    }
+    
+    /// Whether the current file should be rotated
+    pub fn is_due(&self, size: u64, age: Duration) -> bool {
+        self.max_size.is_some_and(|max| size > max)
+            || self.interval.is_some_and(|interval| age >= interval)
+    }
+    
+    /// How often the writer checks rotation while the process is quiet
+    fn check_period(&self) -> Duration {
+        const MAX_CHECK_PERIOD: Duration = Duration::from_secs(60);
+        self.interval
+            .map_or(MAX_CHECK_PERIOD, |interval| interval.min(MAX_CHECK_PERIOD))
+            .max(Duration::from_secs(1))
+    }
+}
+
+/// 当前打开的日志文件
+struct LogFileWriter {
+    /// 日志文件无法打开时为空，此时丢弃日志行
+    file: Option<File>,
+    written: u64,
+    /// 文件创建时间，重新打开文件或重启守护进程后轮转间隔不会重新计算
+    created: SystemTime,
+}
+
+impl LogFileWriter {
+    async fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let metadata = file.metadata().await?;
+        let created = match metadata.created() {
+            Ok(created) => created,
+            // 文件系统不支持创建时间时，使用第一行日志的时间戳
+            Err(_) => first_line_time(path).await.unwrap_or_else(SystemTime::now),
+        };
+        Ok(Self { file: Some(file), written: metadata.len(), created })
+    }
+    
+    /// Writer that drops lines until the log file can be opened again
+    fn closed() -> Self {
+        Self { file: None, written: 0, created: SystemTime::now() }
+    }
+    
+    fn age(&self) -> Duration {
+        SystemTime::now().duration_since(self.created).unwrap_or_default()
+    }
+}
+
+/// Timestamp of the first `[YYYY-MM-DD HH:MM:SS]` line in a log file
+async fn first_line_time(path: &Path) -> Option<SystemTime> {
+    let file = File::open(path).await.ok()?;
+    let line = BufReader::new(file).lines().next_line().await.ok()??;
+    let timestamp = line.strip_prefix('[')?.get(..19)?;
+    let time = chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S").ok()?;
+    Some(time.and_utc().into())
+}
+
+async fn rotate_and_reopen(
+    process_name: &str,
+    log_file: &Path,
+    rotation: &LogRotation,
+    writer: LogFileWriter,
+) -> LogFileWriter {
+    use tokio::io::AsyncWriteExt;
+    
+    if let Some(mut file) = writer.file {
+        let _ = file.flush().await;
+    }
+    
+    let path = log_file.to_path_buf();
+    let (max_files, compress) = (rotation.max_files, rotation.compress);
+    let result = tokio::task::spawn_blocking(move || rotate_files(&path, max_files, compress)).await;
+    match result {
+        Ok(Ok(())) => info!(process = process_name, path = ?log_file, "Log file rotated"),
+        Ok(Err(e)) => error!(process = process_name, error = %e, "Failed to rotate log file"),
+        Err(e) => error!(process = process_name, error = %e, "Log rotation task failed"),
+    }
+    
+    match LogFileWriter::open(log_file).await {
+        Ok(w) => w,
+        Err(e) => {
+            // 无法重新打开时丢弃日志行，写入任务继续读取管道并定时重试
+            error!(process = process_name, error = %e, "Failed to reopen log file after rotation");
+            LogFileWriter::closed()
+        }
+    }
+}
+
+/// Path of the `generation`-th rotated file
+fn rotated_path(log_file: &Path, generation: usize, compressed: bool) -> PathBuf {
+    let mut name = log_file.as_os_str().to_os_string();
+    name.push(format!(".{}", generation));
+    if compressed {
+        name.push(".gz");
+    }
+    PathBuf::from(name)
+}
+
+/// Rotate `log_file` to `log_file.1`, shifting older generations up
+///
+/// Keeps at most `max_files` generations; with `compress` the rotated file is
+/// gzipped to `log_file.1.gz`.
+pub fn rotate_files(log_file: &Path, max_files: usize, compress: bool) -> std::io::Result<()> {
+    if max_files == 0 {
+        return std::fs::remove_file(log_file);
+    }
+    
+    // 删除最旧的一代，然后依次后移
+    for compressed in [false, true] {
+        let oldest = rotated_path(log_file, max_files, compressed);
+        if oldest.exists() {
+            std::fs::remove_file(oldest)?;
+        }
+    }
+    for generation in (1..max_files).rev() {
+        for compressed in [false, true] {
+            let from = rotated_path(log_file, generation, compressed);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(log_file, generation + 1, compressed))?;
+            }
+        }
+    }
+    
+    let first = rotated_path(log_file, 1, false);
+    std::fs::rename(log_file, &first)?;
+    
+    if compress {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        
+        let mut input = std::fs::File::open(&first)?;
+        let output = std::fs::File::create(rotated_path(log_file, 1, true))?;
+        let mut encoder = GzEncoder::new(output, Compression::default());
+        std::io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?;
+        std::fs::remove_file(&first)?;
+    }
+    
+    Ok(())
+}
+
 /// 日志查看器
 pub struct LogViewer {
     log_file: PathBuf,
@@ -207,29 +416,53 @@ impl LogViewer {
     }
     
     /// 实时跟踪日志（返回接收器）
-    pub async fn follow(&self) -> Result<mpsc::Receiver<String>, std::io::Error> {
+    ///
+    /// 先发送最后 `backlog` 行，然后持续轮询新追加的内容；文件被截断时从头读取，
+    /// 被轮转（inode 变化）时读完旧文件后切换到新文件。
+    pub async fn follow(&self, backlog: usize) -> Result<mpsc::Receiver<String>, std::io::Error> {
         let (sender, receiver) = mpsc::channel(100);
         let log_file = self.log_file.clone();
         
         tokio::spawn(async move {
-            use tokio::io::AsyncBufReadExt;
-            
-            // 等待文件存在
-            while !log_file.exists() {
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-            }
+            let mut follower = LogFollower::new(log_file);
             
-            let file = match File::open(&log_file).await {
-                Ok(f) => f,
-                Err(_) => return,
+            // 等待文件存在，首次读取的内容只保留最后 backlog 行
+            let initial = loop {
+                match follower.poll().await {
+                    Ok(lines) if follower.is_open() => break lines,
+                    Ok(_) => tokio::time::sleep(FOLLOW_POLL_INTERVAL).await,
+                    Err(e) => {
+                        debug!(error = %e, "Failed to read log file");
+                        return;
+                    }
+                }
             };
-            
-            let reader = BufReader::new(file).lines();
-            let mut lines = reader;
-            
-            while let Ok(Some(line)) = lines.next_line().await {
+            let skip = initial.len().saturating_sub(backlog);
+            for line in initial.into_iter().skip(skip) {
                 if sender.send(line).await.is_err() {
-                    break;
+                    return;
+                }
+            }
+            
+            loop {
+                let lines = match follower.poll().await {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        debug!(error = %e, "Failed to read log file");
+                        Vec::new()
+                    }
+                };
+                if lines.is_empty() {
+                    if sender.is_closed() {
+                        return;
+                    }
+                    tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+                    continue;
+                }
+                for line in lines {
+                    if sender.send(line).await.is_err() {
+                        return;
+                    }
                 }
             }
         });
@@ -237,47 +470,446 @@ impl LogViewer {
         Ok(receiver)
     }
     
-    /// 按时间过滤日志
-    pub async fn since(&self, since_seconds: i64) -> Result<Vec<String>, std::io::Error> {
-        let cutoff = chrono::Utc::now().timestamp() - since_seconds;
-        let all_lines = self.tail(10000).await?; // 读取最近的 10000 行
-        
-        let filtered: Vec<String> = all_lines
-            .into_iter()
-            .filter(|line| {
-                // 简单的时间过滤（假设格式正确）
-                if let Some(timestamp_str) = line.split('[').nth(1) {
-                    if let Some(time_str) = timestamp_str.split(']').next() {
-                        // 解析时间戳（简化版本）
-                        // 实际应该更严格地解析
-                        return true; // 暂时返回所有行
-                    }
+    /// 按条件过滤日志，返回最后 `limit` 条匹配的行（`None` 表示不限制）
+    pub async fn filter(
+        &self,
+        filter: &LogFilter,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, std::io::Error> {
+        let file = File::open(&self.log_file).await?;
+        let mut lines = BufReader::new(file).lines();
+        let mut matched = std::collections::VecDeque::new();
+        
+        while let Some(line) = lines.next_line().await? {
+            if !filter.matches(&line) {
+                continue;
+            }
+            matched.push_back(line);
+            if limit.is_some_and(|max| matched.len() > max) {
+                matched.pop_front();
+            }
+        }
+        
+        Ok(matched.into())
+    }
+}
+
+/// 日志过滤条件
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// 只保留该时间（UTC 时间戳，含）之后的行
+    pub since: Option<i64>,
+    /// 只保留该时间（UTC 时间戳，含）之前的行
+    pub until: Option<i64>,
+    pub stream: Option<LogStream>,
+    pub pattern: Option<regex::Regex>,
+}
+
+impl LogFilter {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none() && self.stream.is_none() && self.pattern.is_none()
+    }
+    
+    /// Whether a formatted log line passes every condition
+    ///
+    /// Lines without the `[time] [OUT|ERR]` prefix only pass when no time or
+    /// stream condition is set.
+    pub fn matches(&self, line: &str) -> bool {
+        if self.since.is_some() || self.until.is_some() || self.stream.is_some() {
+            let parsed = match parse_log_line(line) {
+                Some(p) => p,
+                None => return false,
+            };
+            if self.since.is_some_and(|t| parsed.timestamp < t)
+                || self.until.is_some_and(|t| parsed.timestamp > t)
+                || self.stream.is_some_and(|s| parsed.stream != s)
+            {
+                return false;
+            }
+        }
+        
+        self.pattern.as_ref().is_none_or(|re| re.is_match(line))
+    }
+}
+
+/// Parse a line written by `start_writer`: `[YYYY-MM-DD HH:MM:SS] [OUT|ERR] content`
+pub fn parse_log_line(line: &str) -> Option<LogLine> {
+    let rest = line.strip_prefix('[')?;
+    let (time_str, rest) = rest.split_once("] [")?;
+    let (stream_str, content) = rest.split_once(']')?;
+    
+    let timestamp = chrono::NaiveDateTime::parse_from_str(time_str, "%Y-%m-%d %H:%M:%S")
+        .ok()?
+        .and_utc()
+        .timestamp();
+    let stream = match stream_str {
+        "OUT" => LogStream::Stdout,
+        "ERR" => LogStream::Stderr,
+        _ => return None,
+    };
+    
+    Some(LogLine {
+        timestamp,
+        stream,
+        content: content.strip_prefix(' ').unwrap_or(content).to_string(),
+    })
+}
+
+/// Parse a `--since`/`--until` value into a UTC timestamp
+///
+/// Accepts a duration before `now` (`90`, `30s`, `10m`, `2h`, `1d`), an RFC 3339
+/// timestamp, or `YYYY-MM-DD[ HH:MM[:SS]]` interpreted as UTC like the log file.
+pub fn parse_time_spec(spec: &str, now: chrono::DateTime<chrono::Utc>) -> Result<i64, String> {
+    let spec = spec.trim();
+    
+    let (number, unit) = spec.split_at(spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len()));
+    if !number.is_empty() {
+        let multiplier = match unit {
+            "" | "s" => Some(1),
+            "m" => Some(60),
+            "h" => Some(3600),
+            "d" => Some(86400),
+            _ => None,
+        };
+        if let (Some(multiplier), Ok(n)) = (multiplier, number.parse::<i64>()) {
+            return Ok(now.timestamp() - n * multiplier);
+        }
+    }
+    
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(spec) {
+        return Ok(dt.timestamp());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(spec, format) {
+            return Ok(dt.and_utc().timestamp());
+        }
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp());
+    }
+    
+    Err(format!(
+        "invalid time '{}': expected a duration like 30s/10m/2h/1d or a timestamp like 2026-03-04 12:00:00",
+        spec
+    ))
+}
+
+/// `follow` 轮询新内容的间隔
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 跟踪单个日志文件的读取位置
+struct LogFollower {
+    path: PathBuf,
+    file: Option<File>,
+    file_id: u64,
+    offset: u64,
+    partial: String,
+}
+
+impl LogFollower {
+    fn new(path: PathBuf) -> Self {
+        Self { path, file: None, file_id: 0, offset: 0, partial: String::new() }
+    }
+    
+    fn is_open(&self) -> bool {
+        self.file.is_some()
+    }
+    
+    /// Read every complete line appended since the last poll
+    async fn poll(&mut self) -> std::io::Result<Vec<String>> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+        
+        let mut lines = Vec::new();
+        
+        if self.file.is_none() {
+            match File::open(&self.path).await {
+                Ok(f) => {
+                    self.file_id = file_id(&f.metadata().await?);
+                    self.offset = 0;
+                    self.file = Some(f);
                 }
-                false
-            })
-            .collect();
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(lines),
+                Err(e) => return Err(e),
+            }
+        }
         
-        Ok(filtered)
+        // 截断：文件变短后从头读取
+        let current = match tokio::fs::metadata(&self.path).await {
+            Ok(m) => Some(m),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        if let (Some(meta), Some(file)) = (&current, self.file.as_mut()) {
+            if file_id(meta) == self.file_id && meta.len() < self.offset {
+                file.seek(std::io::SeekFrom::Start(0)).await?;
+                self.offset = 0;
+                self.partial.clear();
+            }
+        }
+        
+        if let Some(file) = self.file.as_mut() {
+            let mut buf = Vec::new();
+            let n = file.read_to_end(&mut buf).await?;
+            self.offset += n as u64;
+            self.partial.push_str(&String::from_utf8_lossy(&buf));
+            while let Some(pos) = self.partial.find('\n') {
+                let line: String = self.partial.drain(..=pos).collect();
+                lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+            }
+        }
+        
+        // 轮转：旧文件已读完，切换到路径上的新文件
+        if let Some(meta) = current {
+            if file_id(&meta) != self.file_id {
+                if !self.partial.is_empty() {
+                    lines.push(std::mem::take(&mut self.partial));
+                }
+                self.file = None;
+                lines.extend(Box::pin(self.poll()).await?);
+            }
+        }
+        
+        Ok(lines)
     }
 }
 
+#[cfg(unix)]
+fn file_id(meta: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.ino()
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &std::fs::Metadata) -> u64 {
+    0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::NamedTempFile;
+    use tempfile::{NamedTempFile, TempDir};
     
     #[tokio::test]
     async fn test_output_capture() {
         let temp_file = NamedTempFile::new().unwrap();
         let log_path = temp_file.path().to_path_buf();
         
-        let (capture, receiver) = OutputCapture::new(
+        let (capture, _receiver) = OutputCapture::new(
             "test-process".to_string(),
             log_path,
-            None,
+            LogRotation::default(),
         );
         
         // 测试捕获器创建
         assert_eq!(capture.process_name, "test-process");
     }
+    
+    #[test]
+    fn test_rotate_files_keeps_generations() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("app.log");
+        
+        for content in ["first", "second", "third"] {
+            std::fs::write(&log, content).unwrap();
+            rotate_files(&log, 2, false).unwrap();
+        }
+        
+        assert!(!log.exists());
+        assert_eq!(std::fs::read_to_string(rotated_path(&log, 1, false)).unwrap(), "third");
+        assert_eq!(std::fs::read_to_string(rotated_path(&log, 2, false)).unwrap(), "second");
+        assert!(!rotated_path(&log, 3, false).exists());
+    }
+    
+    #[test]
+    fn test_rotate_files_compressed() {
+        use std::io::Read;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("app.log");
+        std::fs::write(&log, "hello").unwrap();
+        rotate_files(&log, 3, true).unwrap();
+        
+        let gz = rotated_path(&log, 1, true);
+        assert!(gz.exists());
+        assert!(!rotated_path(&log, 1, false).exists());
+        
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(gz).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "hello");
+    }
+    
+    #[test]
+    fn test_parse_log_line() {
+        let line = parse_log_line("[2026-03-04 12:00:05] [ERR] boom: [x] failed").unwrap();
+        assert_eq!(line.stream, LogStream::Stderr);
+        assert_eq!(line.content, "boom: [x] failed");
+        assert_eq!(line.timestamp, parse_time_spec("2026-03-04 12:00:05", chrono::Utc::now()).unwrap());
+        
+        assert!(parse_log_line("plain text").is_none());
+        assert!(parse_log_line("[2026-03-04 12:00:05] [DBG] x").is_none());
+    }
+    
+    #[test]
+    fn test_parse_time_spec() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-04T12:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let ts = now.timestamp();
+        
+        assert_eq!(parse_time_spec("90", now).unwrap(), ts - 90);
+        assert_eq!(parse_time_spec("30s", now).unwrap(), ts - 30);
+        assert_eq!(parse_time_spec("10m", now).unwrap(), ts - 600);
+        assert_eq!(parse_time_spec("2h", now).unwrap(), ts - 7200);
+        assert_eq!(parse_time_spec("1d", now).unwrap(), ts - 86400);
+        assert_eq!(parse_time_spec("2026-03-04 11:00:00", now).unwrap(), ts - 3600);
+        assert_eq!(parse_time_spec("2026-03-04T11:30:00", now).unwrap(), ts - 1800);
+        assert_eq!(parse_time_spec("2026-03-04T13:00:00+02:00", now).unwrap(), ts - 3600);
+        assert_eq!(parse_time_spec("2026-03-04", now).unwrap(), ts - 12 * 3600);
+        assert!(parse_time_spec("yesterday", now).is_err());
+        assert!(parse_time_spec("10w", now).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_filter_window_stream_and_grep() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("app.log");
+        std::fs::write(
+            &log,
+            "[2026-03-04 10:00:00] [OUT] starting\n\
+             [2026-03-04 10:05:00] [ERR] timeout talking to db\n\
+             [2026-03-04 10:06:00] [OUT] retrying db\n\
+             [2026-03-04 10:07:00] [ERR] gave up on db\n\
+             [2026-03-04 11:00:00] [ERR] timeout again\n",
+        )
+        .unwrap();
+        
+        let now = chrono::Utc::now();
+        let viewer = LogViewer::new(log);
+        let filter = LogFilter {
+            since: Some(parse_time_spec("2026-03-04 10:05:00", now).unwrap()),
+            until: Some(parse_time_spec("2026-03-04 10:30:00", now).unwrap()),
+            stream: Some(LogStream::Stderr),
+            pattern: None,
+        };
+        let lines = viewer.filter(&filter, None).await.unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("timeout talking to db"));
+        
+        let filter = LogFilter {
+            pattern: Some(regex::Regex::new("time(out)?").unwrap()),
+            ..Default::default()
+        };
+        let lines = viewer.filter(&filter, Some(1)).await.unwrap();
+        assert_eq!(lines, vec!["[2026-03-04 11:00:00] [ERR] timeout again".to_string()]);
+    }
+    
+    #[tokio::test]
+    async fn test_follow_backlog_append_truncate_rotate() {
+        use std::io::Write;
+        
+        async fn next(receiver: &mut mpsc::Receiver<String>) -> String {
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("timed out waiting for line")
+                .expect("follow channel closed")
+        }
+        
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("app.log");
+        std::fs::write(&log, "one\ntwo\nthree\n").unwrap();
+        
+        let viewer = LogViewer::new(log.clone());
+        let mut receiver = viewer.follow(2).await.unwrap();
+        assert_eq!(next(&mut receiver).await, "two");
+        assert_eq!(next(&mut receiver).await, "three");
+        
+        // 追加
+        let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+        writeln!(file, "four").unwrap();
+        assert_eq!(next(&mut receiver).await, "four");
+        
+        // 截断
+        std::fs::write(&log, "five\n").unwrap();
+        assert_eq!(next(&mut receiver).await, "five");
+        
+        // 轮转：旧文件的剩余内容先于新文件输出
+        writeln!(file, "six").unwrap();
+        std::fs::rename(&log, temp_dir.path().join("app.log.1")).unwrap();
+        std::fs::write(&log, "seven\n").unwrap();
+        assert_eq!(next(&mut receiver).await, "six");
+        assert_eq!(next(&mut receiver).await, "seven");
+    }
+    
+    #[tokio::test]
+    async fn test_writer_rotates_by_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("app.log");
+        
+        let rotation = LogRotation { max_size: Some(64), max_files: 2, ..Default::default() };
+        let (capture, receiver) = OutputCapture::new("test".to_string(), log.clone(), rotation);
+        let (_reopen_tx, reopen_rx) = broadcast::channel(1);
+        capture.start_writer(receiver, reopen_rx);
+        
+        for i in 0..4 {
+            capture.sender.send(LogLine {
+                timestamp: 0,
+                stream: LogStream::Stdout,
+                content: format!("line {}", i),
+            }).unwrap();
+        }
+        
+        for _ in 0..50 {
+            if rotated_path(&log, 1, false).exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(rotated_path(&log, 1, false).exists());
+    }
+    
+    #[tokio::test]
+    async fn test_writer_rotates_quiet_file_by_age() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("app.log");
+        // 文件创建时间早于轮转间隔，模拟守护进程重启前已写了一段时间的日志
+        std::fs::write(&log, "[2020-01-01 00:00:00] [OUT] old\n").unwrap();
+        
+        let rotation = LogRotation { interval: Some(Duration::from_secs(1)), max_files: 2, ..Default::default() };
+        let (capture, receiver) = OutputCapture::new("test".to_string(), log.clone(), rotation);
+        let (_reopen_tx, reopen_rx) = broadcast::channel(1);
+        capture.start_writer(receiver, reopen_rx);
+        
+        // 没有任何新的输出
+        for _ in 0..100 {
+            if rotated_path(&log, 1, false).exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(rotated_path(&log, 1, false).exists());
+        assert!(std::fs::read_to_string(rotated_path(&log, 1, false)).unwrap().contains("old"));
+    }
+    
+    #[tokio::test]
+    async fn test_writer_keeps_draining_without_log_file() {
+        let temp_dir = TempDir::new().unwrap();
+        // 父路径是普通文件，日志文件永远无法打开
+        let blocker = temp_dir.path().join("blocker");
+        std::fs::write(&blocker, "").unwrap();
+        let log = blocker.join("app.log");
+        
+        let (capture, receiver) = OutputCapture::new("test".to_string(), log, LogRotation::default());
+        let (_reopen_tx, reopen_rx) = broadcast::channel(1);
+        capture.start_writer(receiver, reopen_rx);
+        
+        for i in 0..10 {
+            capture.sender.send(LogLine {
+                timestamp: 0,
+                stream: LogStream::Stdout,
+                content: format!("line {}", i),
+            }).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!capture.sender.is_closed());
+    }
 }
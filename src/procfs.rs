@@ -0,0 +1,150 @@
+//! 从 `/proc` 读取进程信息
+//!
+//! 非 Linux 平台上 `/proc` 不存在，所有函数返回 `None`。
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// `/proc/<pid>/stat` 中用到的字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcStat {
+    /// 进程组 ID
+    pub pgrp: u32,
+    /// 累计用户态 + 内核态 CPU 时间
+    pub cpu_time: Duration,
+    /// 线程数
+    pub threads: u64,
+}
+
+pub fn read_stat(pid: u32) -> Option<ProcStat> {
+    let content = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    parse_stat(&content)
+}
+
+fn parse_stat(content: &str) -> Option<ProcStat> {
+    // 进程名可能包含空格和括号，从最后一个 ')' 之后开始解析；fields[0] 是第 3 个字段 state
+    let fields: Vec<&str> = content.get(content.rfind(')')? + 2..)?.split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+    let ticks = clock_ticks();
+    Some(ProcStat {
+        pgrp: field(5)? as u32,
+        cpu_time: Duration::from_secs_f64((field(14)? + field(15)?) as f64 / ticks as f64),
+        threads: field(20)?,
+    })
+}
+
+/// Cumulative user + system CPU time of a process
+pub fn read_cpu_time(pid: u32) -> Option<Duration> {
+    read_stat(pid).map(|s| s.cpu_time)
+}
+
+/// Resident set size in bytes
+pub fn read_rss(pid: u32) -> Option<u64> {
+    let content = std::fs::read_to_string(format!("/proc/{}/statm", pid)).ok()?;
+    let pages: u64 = content.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * page_size())
+}
+
+/// Number of open file descriptors (needs permission to read `/proc/<pid>/fd`)
+pub fn fd_count(pid: u32) -> Option<u64> {
+    std::fs::read_dir(format!("/proc/{}/fd", pid)).ok().map(|dir| dir.count() as u64)
+}
+
+/// 进程组 ID 到组内进程的索引
+///
+/// 遍历一次 `/proc` 建立，同一次状态查询中的所有进程共用，避免每个进程都遍历一遍。
+#[derive(Debug, Default)]
+pub struct ProcessGroups {
+    members: HashMap<u32, Vec<u32>>,
+}
+
+impl ProcessGroups {
+    /// Scan `/proc` once and index every process by its process group
+    pub fn scan() -> Self {
+        let mut members: HashMap<u32, Vec<u32>> = HashMap::new();
+        if let Ok(dir) = std::fs::read_dir("/proc") {
+            for entry in dir.flatten() {
+                let pid = match entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) {
+                    Some(pid) => pid,
+                    None => continue,
+                };
+                if let Some(stat) = read_stat(pid) {
+                    members.entry(stat.pgrp).or_default().push(pid);
+                }
+            }
+        }
+        Self { members }
+    }
+
+    /// Summed RSS of every process in the process group `pgid`
+    pub fn group_rss(&self, pgid: u32) -> Option<u64> {
+        self.members
+            .get(&pgid)?
+            .iter()
+            .filter_map(|pid| read_rss(*pid))
+            .fold(None, |total, rss| Some(total.unwrap_or(0) + rss))
+    }
+}
+
+fn clock_ticks() -> u64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf 没有副作用
+        let ticks = unsafe { nix::libc::sysconf(nix::libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            return ticks as u64;
+        }
+    }
+    100
+}
+
+fn page_size() -> u64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf 没有副作用
+        let size = unsafe { nix::libc::sysconf(nix::libc::_SC_PAGESIZE) };
+        if size > 0 {
+            return size as u64;
+        }
+    }
+    4096
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let line = "1234 (php: master (x)) S 1 1234 1234 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 7 0 100 0 0";
+        let stat = parse_stat(line).unwrap();
+        assert_eq!(stat.pgrp, 1234);
+        assert_eq!(stat.threads, 7);
+        assert_eq!(stat.cpu_time, Duration::from_secs_f64(300.0 / clock_ticks() as f64));
+        assert!(parse_stat("garbage").is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_group_rss_sums_children() {
+        let mut child = tokio::process::Command::new("/bin/sh")
+            .args(["-c", "sleep 30 & sleep 30 & wait"])
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let own = read_rss(pid).unwrap();
+        let groups = ProcessGroups::scan();
+        let group = groups.group_rss(pid).unwrap();
+        assert!(groups.group_rss(u32::MAX).is_none());
+        assert!(group > own, "group {} own {}", group, own);
+        assert!(read_stat(pid).unwrap().threads >= 1);
+        assert!(fd_count(pid).unwrap() >= 3);
+        assert!(read_cpu_time(u32::MAX).is_none());
+
+        nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pid as i32), nix::sys::signal::Signal::SIGKILL).unwrap();
+        let _ = child.wait().await;
+    }
+}
@@ -0,0 +1,172 @@
+//! 重启退避与崩溃循环检测
+//!
+//! 自动重启的进程每次意外退出时，由 `RestartTracker` 根据 `RestartPolicy`
+//! 计算下一次重启前的等待时间；在 `restart_window` 内重启次数达到
+//! `max_restarts` 后进入 Fatal 状态，直到运维人员手动启动。
+
+use crate::config::ProcessConfig;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 单个进程的重启策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartPolicy {
+    pub restart_delay: Duration,
+    pub backoff_multiplier: f64,
+    pub max_backoff: Duration,
+    pub max_restarts: u32,
+    pub restart_window: Duration,
+    pub min_uptime: Duration,
+}
+
+impl RestartPolicy {
+    pub fn from_config(config: &ProcessConfig) -> Self {
+        Self {
+            restart_delay: Duration::from_secs(config.restart_delay),
+            backoff_multiplier: config.backoff_multiplier.max(1.0),
+            max_backoff: Duration::from_secs(config.max_backoff),
+            max_restarts: config.max_restarts,
+            restart_window: Duration::from_secs(config.restart_window),
+            min_uptime: Duration::from_secs(config.min_uptime),
+        }
+    }
+
+    /// Delay before the next restart after `failures` consecutive failed starts
+    pub fn delay_for(&self, failures: u32) -> Duration {
+        let factor = self.backoff_multiplier.powi(failures.min(64) as i32);
+        let delay = self.restart_delay.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// 对一次意外退出的处理决定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestartDecision {
+    /// 等待指定时间后重启
+    Restart(Duration),
+    /// 重启过于频繁，放弃重启
+    Fatal,
+}
+
+/// 单个进程的重启记录
+#[derive(Debug, Default)]
+pub struct RestartTracker {
+    /// 连续启动失败次数（运行时间低于 `min_uptime`）
+    consecutive_failures: u32,
+    /// `restart_window` 内的重启时间点
+    restarts: VecDeque<Instant>,
+    /// 等待中的重启时间
+    pub next_restart: Option<Instant>,
+    /// 是否已进入 Fatal 状态
+    pub fatal: bool,
+}
+
+impl RestartTracker {
+    /// Decide what to do after the process exited unexpectedly
+    pub fn on_exit(&mut self, policy: &RestartPolicy, uptime: Duration, now: Instant) -> RestartDecision {
+        if uptime < policy.min_uptime {
+            self.consecutive_failures += 1;
+        } else {
+            self.consecutive_failures = 0;
+        }
+
+        while let Some(&first) = self.restarts.front() {
+            if now.duration_since(first) > policy.restart_window {
+                self.restarts.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self.restarts.len() as u32 >= policy.max_restarts {
+            self.fatal = true;
+            self.next_restart = None;
+            return RestartDecision::Fatal;
+        }
+
+        self.restarts.push_back(now);
+        let delay = policy.delay_for(self.consecutive_failures.saturating_sub(1));
+        self.next_restart = Some(now + delay);
+        RestartDecision::Restart(delay)
+    }
+
+    /// Whether a pending restart is due
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.next_restart.is_some_and(|t| now >= t)
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            restart_delay: Duration::from_secs(1),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_secs(10),
+            max_restarts: 5,
+            restart_window: Duration::from_secs(60),
+            min_uptime: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = policy();
+        let mut tracker = RestartTracker::default();
+        let start = Instant::now();
+
+        let delays: Vec<Duration> = (0..5)
+            .map(|i| {
+                match tracker.on_exit(&policy, Duration::ZERO, start + Duration::from_secs(i)) {
+                    RestartDecision::Restart(d) => d,
+                    RestartDecision::Fatal => panic!("unexpected fatal"),
+                }
+            })
+            .collect();
+
+        let secs: Vec<u64> = delays.iter().map(|d| d.as_secs()).collect();
+        assert_eq!(secs, vec![1, 2, 4, 8, 10]);
+        assert_eq!(tracker.consecutive_failures(), 5);
+    }
+
+    #[test]
+    fn test_crash_loop_becomes_fatal() {
+        let policy = policy();
+        let mut tracker = RestartTracker::default();
+        let start = Instant::now();
+
+        for i in 0..5 {
+            let decision = tracker.on_exit(&policy, Duration::ZERO, start + Duration::from_secs(i));
+            assert!(matches!(decision, RestartDecision::Restart(_)));
+        }
+        let decision = tracker.on_exit(&policy, Duration::ZERO, start + Duration::from_secs(5));
+        assert_eq!(decision, RestartDecision::Fatal);
+        assert!(tracker.fatal);
+        assert!(tracker.next_restart.is_none());
+    }
+
+    #[test]
+    fn test_window_expiry_and_healthy_run_reset() {
+        let policy = policy();
+        let mut tracker = RestartTracker::default();
+        let start = Instant::now();
+
+        for i in 0..5 {
+            tracker.on_exit(&policy, Duration::ZERO, start + Duration::from_secs(i));
+        }
+
+        // 窗口之外的重启不再计数，且运行时间足够长视为成功启动
+        let later = start + Duration::from_secs(120);
+        let decision = tracker.on_exit(&policy, Duration::from_secs(100), later);
+        assert_eq!(decision, RestartDecision::Restart(Duration::from_secs(1)));
+        assert_eq!(tracker.consecutive_failures(), 0);
+        assert!(!tracker.is_due(later));
+        assert!(tracker.is_due(later + Duration::from_secs(1)));
+    }
+}
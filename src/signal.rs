@@ -5,6 +5,7 @@ use tokio::sync::mpsc;
 pub enum Signal {
     Shutdown,
     ReloadConfig,
+    ReopenLogs,
 }
 
 pub struct SignalHandler {
@@ -20,16 +21,18 @@ impl SignalHandler {
             use signal_hook::consts::SIGTERM;
             use signal_hook::consts::SIGINT;
             use signal_hook::consts::SIGHUP;
+            use signal_hook::consts::SIGUSR1;
             use signal_hook::iterator::Signals;
             use std::thread;
             
-            if let Ok(mut signals) = Signals::new(&[SIGTERM, SIGINT, SIGHUP]) {
+            if let Ok(mut signals) = Signals::new([SIGTERM, SIGINT, SIGHUP, SIGUSR1]) {
                 let tx_clone = tx.clone();
                 thread::spawn(move || {
                     for sig in signals.forever() {
-                        let signal = match sig as i32 {
+                        let signal = match sig {
                             SIGTERM | SIGINT => Signal::Shutdown,
                             SIGHUP => Signal::ReloadConfig,
+                            SIGUSR1 => Signal::ReopenLogs,
                             _ => continue,
                         };
                         let _ = tx_clone.send(signal);
@@ -45,3 +48,33 @@ impl SignalHandler {
         self.rx.recv().await
     }
 }
+
+/// Parse a signal name such as "SIGUSR1", "usr1" or "15"
+#[cfg(unix)]
+pub fn parse(name: &str) -> Result<nix::sys::signal::Signal, DaemonError> {
+    use nix::sys::signal::Signal;
+    use std::str::FromStr;
+
+    let invalid = || DaemonError::Config(format!("Unknown signal '{}'", name));
+    if let Ok(number) = name.parse::<i32>() {
+        return Signal::try_from(number).map_err(|_| invalid());
+    }
+    let upper = name.trim().to_ascii_uppercase();
+    let full = if upper.starts_with("SIG") { upper } else { format!("SIG{}", upper) };
+    Signal::from_str(&full).map_err(|_| invalid())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::sys::signal::Signal;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("SIGUSR1").unwrap(), Signal::SIGUSR1);
+        assert_eq!(parse("quit").unwrap(), Signal::SIGQUIT);
+        assert_eq!(parse("2").unwrap(), Signal::SIGINT);
+        assert!(parse("SIGNOPE").is_err());
+        assert!(parse("0").is_err());
+    }
+}
@@ -6,10 +6,11 @@ use rusqlite::{Connection, Result as SqliteResult};
 use rusqlite::OptionalExtension;
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
-use tracing::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
 
 /// 进程历史记录
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessHistory {
     pub id: i64,
     pub name: String,
@@ -19,6 +20,39 @@ pub struct ProcessHistory {
     pub exit_code: Option<i32>,
     pub restart_count: u32,
     pub auto_restart: bool,
+    pub start_reason: Option<String>,
+    pub end_reason: Option<String>,
+}
+
+/// 调度任务的一次执行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+    pub id: i64,
+    pub name: String,
+    pub pid: u32,
+    /// 计划执行时间，手动触发时为空
+    pub scheduled_time: Option<DateTime<Utc>>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// 执行耗时（秒）
+    pub duration: Option<f64>,
+    pub exit_code: Option<i32>,
+    /// 是否因超过 timeout 被终止
+    pub timed_out: bool,
+}
+
+impl JobRun {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0) && !self.timed_out
+    }
+}
+
+/// 默认数据库路径
+pub fn default_db_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mi7soft-daemon")
+        .join("daemon.db")
 }
 
 /// 进程统计信息
@@ -85,26 +119,64 @@ impl Storage {
                 last_start_time TEXT,
                 last_exit_code INTEGER
             );
+            
+            -- 调度任务执行记录表
+            CREATE TABLE IF NOT EXISTS job_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                pid INTEGER NOT NULL,
+                scheduled_time TEXT,
+                start_time TEXT NOT NULL,
+                end_time TEXT,
+                duration REAL,
+                exit_code INTEGER,
+                timed_out BOOLEAN DEFAULT 0
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_job_runs_name ON job_runs(name, start_time);
             "#,
         )?;
         
+        self.add_column_if_missing("process_history", "start_reason", "TEXT")?;
+        self.add_column_if_missing("process_history", "end_reason", "TEXT")?;
+        
         debug!("Database tables initialized");
         Ok(())
     }
     
+    /// 为旧版本数据库补充新增的列
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> SqliteResult<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .any(|name| name == column);
+        
+        if !exists {
+            self.conn.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))?;
+            debug!(table = table, column = column, "Database column added");
+        }
+        Ok(())
+    }
+    
     /// 记录进程启动
     pub fn record_start(
         &self,
         name: &str,
         pid: u32,
         auto_restart: bool,
+        reason: &str,
     ) -> SqliteResult<i64> {
         let now = Utc::now().to_rfc3339();
         
         self.conn.execute(
-            "INSERT INTO process_history (name, pid, start_time, auto_restart)
-             VALUES (?1, ?2, ?3, ?4)",
-            (name, pid as i64, now.clone(), auto_restart),
+            "INSERT INTO process_history (name, pid, start_time, auto_restart, start_reason)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (name, pid as i64, now.clone(), auto_restart, reason),
         )?;
         
         let id = self.conn.last_insert_rowid();
@@ -129,15 +201,16 @@ impl Storage {
         name: &str,
         pid: u32,
         exit_code: Option<i32>,
+        reason: &str,
     ) -> SqliteResult<()> {
         let now = Utc::now().to_rfc3339();
         
         // 更新历史记录
         let rows_affected = self.conn.execute(
             "UPDATE process_history
-             SET end_time = ?1, exit_code = ?2
-             WHERE name = ?3 AND pid = ?4 AND end_time IS NULL",
-            (now, exit_code, name, pid as i64),
+             SET end_time = ?1, exit_code = ?2, end_reason = ?3
+             WHERE name = ?4 AND pid = ?5 AND end_time IS NULL",
+            (now, exit_code, reason, name, pid as i64),
         )?;
         
         // 更新统计
@@ -189,7 +262,8 @@ impl Storage {
     /// 获取进程历史
     pub fn get_history(&self, name: &str, limit: usize) -> SqliteResult<Vec<ProcessHistory>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, pid, start_time, end_time, exit_code, restart_count, auto_restart
+            "SELECT id, name, pid, start_time, end_time, exit_code, restart_count, auto_restart,
+                    start_reason, end_reason
              FROM process_history
              WHERE name = ?1
              ORDER BY start_time DESC
@@ -210,6 +284,8 @@ impl Storage {
                 exit_code: row.get(5)?,
                 restart_count: row.get::<_, i64>(6)? as u32,
                 auto_restart: row.get(7)?,
+                start_reason: row.get(8)?,
+                end_reason: row.get(9)?,
             })
         })?.collect::<Result<Vec<_>, _>>()?;
         
@@ -282,6 +358,87 @@ impl Storage {
         Ok(stats)
     }
     
+    /// 记录调度任务开始执行
+    pub fn record_job_start(
+        &self,
+        name: &str,
+        pid: u32,
+        scheduled_time: Option<DateTime<Utc>>,
+    ) -> SqliteResult<i64> {
+        self.conn.execute(
+            "INSERT INTO job_runs (name, pid, scheduled_time, start_time) VALUES (?1, ?2, ?3, ?4)",
+            (name, pid as i64, scheduled_time.map(|t| t.to_rfc3339()), Utc::now().to_rfc3339()),
+        )?;
+        let id = self.conn.last_insert_rowid();
+        debug!(process = name, pid = pid, record_id = id, "Job run start recorded");
+        Ok(id)
+    }
+    
+    /// 记录调度任务执行结束；不是调度任务的进程没有对应记录，不做任何修改
+    pub fn record_job_end(
+        &self,
+        name: &str,
+        pid: u32,
+        exit_code: Option<i32>,
+        timed_out: bool,
+    ) -> SqliteResult<()> {
+        let run: Option<(i64, String)> = self.conn
+            .query_row(
+                "SELECT id, start_time FROM job_runs
+                 WHERE name = ?1 AND pid = ?2 AND end_time IS NULL
+                 ORDER BY id DESC LIMIT 1",
+                (name, pid as i64),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (id, start_time) = match run {
+            Some(run) => run,
+            None => return Ok(()),
+        };
+        
+        let now = Utc::now();
+        let duration = DateTime::parse_from_rfc3339(&start_time)
+            .map(|start| (now - start.with_timezone(&Utc)).num_milliseconds() as f64 / 1000.0)
+            .ok();
+        self.conn.execute(
+            "UPDATE job_runs SET end_time = ?1, duration = ?2, exit_code = ?3, timed_out = ?4 WHERE id = ?5",
+            (now.to_rfc3339(), duration, exit_code, timed_out, id),
+        )?;
+        debug!(process = name, pid = pid, exit_code = exit_code, timed_out = timed_out, "Job run end recorded");
+        Ok(())
+    }
+    
+    /// 获取调度任务最近的执行记录
+    pub fn get_job_runs(&self, name: &str, limit: usize) -> SqliteResult<Vec<JobRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, pid, scheduled_time, start_time, end_time, duration, exit_code, timed_out
+             FROM job_runs
+             WHERE name = ?1
+             ORDER BY id DESC
+             LIMIT ?2"
+        )?;
+        let parse = |s: Option<String>| {
+            s.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                .map(|dt| dt.with_timezone(&Utc))
+        };
+        
+        let runs = stmt.query_map((name, limit as i64), |row| {
+            Ok(JobRun {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                pid: row.get::<_, i64>(2)? as u32,
+                scheduled_time: parse(row.get(3)?),
+                start_time: parse(row.get(4)?).unwrap_or_else(Utc::now),
+                end_time: parse(row.get(5)?),
+                duration: row.get(6)?,
+                exit_code: row.get(7)?,
+                timed_out: row.get(8)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(runs)
+    }
+    
     /// 清理旧记录（保留最近 N 天）
     pub fn cleanup_old_records(&self, days_to_keep: u32) -> SqliteResult<usize> {
         let cutoff = Utc::now() - chrono::Duration::days(days_to_keep as i64);
@@ -289,6 +446,10 @@ impl Storage {
         
         let rows_deleted = self.conn.execute(
             "DELETE FROM process_history WHERE start_time < ?1 AND end_time IS NOT NULL",
+            (cutoff_str.clone(),),
+        )?;
+        self.conn.execute(
+            "DELETE FROM job_runs WHERE start_time < ?1 AND end_time IS NOT NULL",
             (cutoff_str,),
         )?;
         
@@ -310,17 +471,19 @@ mod tests {
         let storage = Storage::new(db_path).unwrap();
         
         // 记录启动
-        let id = storage.record_start("test-process", 1234, true).unwrap();
+        let id = storage.record_start("test-process", 1234, true, "startup").unwrap();
         assert!(id > 0);
         
         // 记录结束
-        storage.record_end("test-process", 1234, Some(0)).unwrap();
+        storage.record_end("test-process", 1234, Some(0), "exit code 0").unwrap();
         
         // 获取历史
         let history = storage.get_history("test-process", 10).unwrap();
         assert_eq!(history.len(), 1);
         assert_eq!(history[0].name, "test-process");
         assert_eq!(history[0].pid, 1234);
+        assert_eq!(history[0].start_reason.as_deref(), Some("startup"));
+        assert_eq!(history[0].end_reason.as_deref(), Some("exit code 0"));
     }
     
     #[test]
@@ -331,15 +494,77 @@ mod tests {
         let storage = Storage::new(db_path).unwrap();
         
         // 记录多次启动
-        storage.record_start("test", 1234, true).unwrap();
-        storage.record_end("test", 1234, Some(0)).unwrap();
+        storage.record_start("test", 1234, true, "startup").unwrap();
+        storage.record_end("test", 1234, Some(0), "exit code 0").unwrap();
+        storage.update_uptime("test", 10).unwrap();
         
-        storage.record_start("test", 5678, true).unwrap();
-        storage.record_end("test", 5678, Some(1)).unwrap();
+        storage.record_start("test", 5678, true, "auto_restart").unwrap();
+        storage.record_restart("test").unwrap();
+        storage.record_end("test", 5678, Some(1), "exit code 1").unwrap();
+        storage.update_uptime("test", 30).unwrap();
         
         // 获取统计
         let stats = storage.get_stats("test").unwrap().unwrap();
         assert_eq!(stats.total_starts, 2);
         assert_eq!(stats.total_failures, 1);
+        assert_eq!(stats.total_restarts, 1);
+        assert_eq!(stats.avg_uptime_seconds, 20.0);
+        assert_eq!(stats.last_exit_code, Some(1));
+        
+        let all = storage.get_all_stats().unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].name, "test");
+    }
+    
+    #[test]
+    fn test_job_runs() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path().join("test.db")).unwrap();
+        let slot = DateTime::parse_from_rfc3339("2026-03-01T03:00:00Z").unwrap().with_timezone(&Utc);
+        
+        storage.record_job_start("backup", 100, Some(slot)).unwrap();
+        storage.record_job_end("backup", 100, Some(0), false).unwrap();
+        storage.record_job_start("backup", 200, None).unwrap();
+        storage.record_job_end("backup", 200, None, true).unwrap();
+        storage.record_job_start("backup", 300, None).unwrap();
+        // 非调度进程没有执行记录
+        storage.record_job_end("web", 400, Some(1), false).unwrap();
+        
+        let runs = storage.get_job_runs("backup", 10).unwrap();
+        assert_eq!(runs.iter().map(|r| r.pid).collect::<Vec<_>>(), vec![300, 200, 100]);
+        assert!(runs[0].end_time.is_none() && runs[0].duration.is_none());
+        assert!(runs[1].timed_out && !runs[1].succeeded());
+        assert!(runs[2].succeeded());
+        assert_eq!(runs[2].scheduled_time, Some(slot));
+        assert!(runs[2].duration.unwrap() >= 0.0);
+        assert!(storage.get_job_runs("web", 10).unwrap().is_empty());
+        assert_eq!(storage.get_job_runs("backup", 1).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_migrate_old_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("old.db");
+        
+        // 旧版本的表结构没有 reason 列
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE process_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                pid INTEGER NOT NULL,
+                start_time TEXT NOT NULL,
+                end_time TEXT,
+                exit_code INTEGER,
+                restart_count INTEGER DEFAULT 0,
+                auto_restart BOOLEAN DEFAULT 0
+            );"
+        ).unwrap();
+        drop(conn);
+        
+        let storage = Storage::new(db_path).unwrap();
+        storage.record_start("test", 1, false, "manual").unwrap();
+        let history = storage.get_history("test", 1).unwrap();
+        assert_eq!(history[0].start_reason.as_deref(), Some("manual"));
     }
 }
@@ -0,0 +1,189 @@
+//! 内存 / CPU 看门狗
+//!
+//! 长期运行的 PHP worker 会缓慢泄漏内存。守护进程主循环每秒对配置了
+//! `max_memory`、`max_cpu_percent` 或 `restart_after` 的进程采样，超过阈值时
+//! 通过 `ProcessManager::restart` 优雅重启，并把原因写入历史记录。
+
+use crate::config::ProcessConfig;
+use crate::formatter::format_memory;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// 单个进程的看门狗阈值
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watchdog {
+    pub max_memory: Option<u64>,
+    pub max_cpu_percent: Option<f64>,
+    pub cpu_window: Duration,
+    pub restart_after: Option<Duration>,
+}
+
+/// 触发重启的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    Memory { used: u64, limit: u64 },
+    Cpu { percent: f64, limit: f64 },
+    Uptime { uptime: Duration },
+}
+
+impl Violation {
+    /// Short reason recorded in storage
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Violation::Memory { .. } => "max_memory",
+            Violation::Cpu { .. } => "max_cpu_percent",
+            Violation::Uptime { .. } => "restart_after",
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Memory { used, limit } => write!(
+                f,
+                "memory {} exceeds max_memory {}",
+                format_memory(Some(*used)),
+                format_memory(Some(*limit))
+            ),
+            Violation::Cpu { percent, limit } => {
+                write!(f, "CPU {:.1}% exceeds max_cpu_percent {:.1}%", percent, limit)
+            }
+            Violation::Uptime { uptime } => write!(f, "uptime {}s reached restart_after", uptime.as_secs()),
+        }
+    }
+}
+
+impl Watchdog {
+    /// `None` when the process has no watchdog thresholds
+    pub fn from_config(config: &ProcessConfig) -> Option<Self> {
+        if config.max_memory.is_none() && config.max_cpu_percent.is_none() && config.restart_after.is_none() {
+            return None;
+        }
+        Some(Self {
+            max_memory: config.max_memory,
+            max_cpu_percent: config.max_cpu_percent,
+            cpu_window: Duration::from_secs(config.cpu_window.max(1)),
+            restart_after: config.restart_after.map(Duration::from_secs),
+        })
+    }
+
+    /// Check one sample of a process against the thresholds
+    pub fn check(&self, sampler: &CpuSampler, memory: Option<u64>, uptime: Duration) -> Option<Violation> {
+        if let (Some(limit), Some(used)) = (self.max_memory, memory) {
+            if used > limit {
+                return Some(Violation::Memory { used, limit });
+            }
+        }
+        if let (Some(limit), Some(percent)) = (self.max_cpu_percent, sampler.average(self.cpu_window)) {
+            if percent > limit {
+                return Some(Violation::Cpu { percent, limit });
+            }
+        }
+        if let Some(restart_after) = self.restart_after {
+            if uptime >= restart_after {
+                return Some(Violation::Uptime { uptime });
+            }
+        }
+        None
+    }
+}
+
+/// 累计 CPU 时间的采样，用于计算一段窗口内的平均 CPU 占用
+#[derive(Debug, Default)]
+pub struct CpuSampler {
+    /// (采样时间, 累计 CPU 时间)
+    samples: VecDeque<(Instant, Duration)>,
+}
+
+impl CpuSampler {
+    /// Record the cumulative CPU time of the process, keeping samples that cover `window`
+    pub fn push(&mut self, now: Instant, cpu_time: Duration, window: Duration) {
+        self.samples.push_back((now, cpu_time));
+        // 保留刚好覆盖窗口的最旧样本
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= window {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Average CPU percent over `window`, `None` until the samples cover the whole window
+    pub fn average(&self, window: Duration) -> Option<f64> {
+        let (first_at, first_cpu) = *self.samples.front()?;
+        let (last_at, last_cpu) = *self.samples.back()?;
+        let elapsed = last_at.duration_since(first_at);
+        if elapsed < window || elapsed.is_zero() {
+            return None;
+        }
+        Some(last_cpu.saturating_sub(first_cpu).as_secs_f64() / elapsed.as_secs_f64() * 100.0)
+    }
+
+    /// CPU percent between the two most recent samples
+    pub fn latest(&self) -> Option<f64> {
+        let mut recent = self.samples.iter().rev();
+        let (last_at, last_cpu) = *recent.next()?;
+        let (prev_at, prev_cpu) = *recent.next()?;
+        let elapsed = last_at.duration_since(prev_at);
+        if elapsed.is_zero() {
+            return None;
+        }
+        Some(last_cpu.saturating_sub(prev_cpu).as_secs_f64() / elapsed.as_secs_f64() * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watchdog() -> Watchdog {
+        Watchdog {
+            max_memory: Some(100 * 1024 * 1024),
+            max_cpu_percent: Some(80.0),
+            cpu_window: Duration::from_secs(10),
+            restart_after: Some(Duration::from_secs(3600)),
+        }
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let wd = watchdog();
+        let sampler = CpuSampler::default();
+
+        assert_eq!(wd.check(&sampler, Some(50 * 1024 * 1024), Duration::from_secs(10)), None);
+
+        let violation = wd.check(&sampler, Some(150 * 1024 * 1024), Duration::from_secs(10)).unwrap();
+        assert_eq!(violation.reason(), "max_memory");
+        assert_eq!(violation.to_string(), "memory 150.0 MB exceeds max_memory 100.0 MB");
+
+        let violation = wd.check(&sampler, None, Duration::from_secs(3600)).unwrap();
+        assert_eq!(violation.reason(), "restart_after");
+    }
+
+    #[test]
+    fn test_cpu_must_be_sustained_over_window() {
+        let wd = watchdog();
+        let mut sampler = CpuSampler::default();
+        let start = Instant::now();
+
+        // 前 5 秒满负载，窗口尚未覆盖，不触发
+        for i in 0..=5 {
+            sampler.push(start + Duration::from_secs(i), Duration::from_secs(i), wd.cpu_window);
+            assert_eq!(wd.check(&sampler, None, Duration::ZERO), None);
+        }
+        // 之后空闲 5 秒，窗口平均 50%，不触发
+        for i in 6..=10 {
+            sampler.push(start + Duration::from_secs(i), Duration::from_secs(5), wd.cpu_window);
+        }
+        assert_eq!(sampler.average(wd.cpu_window).map(|p| p.round()), Some(50.0));
+        assert_eq!(wd.check(&sampler, None, Duration::ZERO), None);
+        assert_eq!(sampler.latest(), Some(0.0));
+
+        // 再满负载 10 秒，窗口内平均 100%，触发
+        for i in 11..=20 {
+            sampler.push(start + Duration::from_secs(i), Duration::from_secs(i - 5), wd.cpu_window);
+        }
+        let violation = wd.check(&sampler, None, Duration::ZERO).unwrap();
+        assert_eq!(violation.reason(), "max_cpu_percent");
+        assert!(sampler.samples.len() <= 12);
+    }
+}